@@ -0,0 +1,41 @@
+use crate::lexer::token::Span;
+
+// Renders a rustc style snippet of the line `span` starts on, with carets under the spanned code:
+//
+//    error: Expected next token to be RPAREN, got LBRACE instead.
+//     --> 1:12
+//      |
+//    1 | if (x > 10 {
+//      |            ^
+pub fn render(source: &str, span: Span, message: &str) -> String {
+   let mut out: String = String::new();
+   out.push_str(format!("error: {}\n", message).as_str());
+   out.push_str(format!("{}--> {}\n", " ".repeat(gutter_width(span)), span).as_str());
+
+   let line: &str = match source.lines().nth(span.line.saturating_sub(1)) {
+      Some(line) => line,
+      None => return out,
+   };
+   let gutter: String = " ".repeat(gutter_width(span) + 1);
+
+   // Keep tabs in the padding so the carets line up with the code above them
+   let padding: String = line.chars()
+      .take(span.column.saturating_sub(1))
+      .map(|c| if c == '\t' { '\t' } else { ' ' })
+      .collect();
+
+   // Only underline the part of the span that is on the first line
+   let start: usize = span.start.min(source.len());
+   let line_end: usize = start + source[start..].find('\n').unwrap_or(source.len() - start);
+   let carets: usize = source[start..span.end.clamp(start, line_end)].chars().count().max(1);
+
+   out.push_str(format!("{}|\n", gutter).as_str());
+   out.push_str(format!("{} | {}\n", span.line, line).as_str());
+   out.push_str(format!("{}| {}{}\n", gutter, padding, "^".repeat(carets)).as_str());
+
+   out
+}
+
+fn gutter_width(span: Span) -> usize {
+   span.line.to_string().len()
+}
@@ -11,6 +11,7 @@ lazy_static! {
       map.insert("exit".to_string(), BuiltIn { func: exit });
 
       map.insert("len".to_string(), BuiltIn { func: len });
+      map.insert("byte_len".to_string(), BuiltIn { func: byte_len });
       map.insert("first".to_string(), BuiltIn { func: first });
       map.insert("last".to_string(), BuiltIn { func: last });
       map.insert("rest".to_string(), BuiltIn { func: rest });
@@ -18,6 +19,7 @@ lazy_static! {
       map.insert("print".to_string(), BuiltIn { func: print });
       map.insert("append".to_string(), BuiltIn { func: append });
       map.insert("insert".to_string(), BuiltIn { func: insert });
+      map.insert("range".to_string(), BuiltIn { func: range });
 
       map
    };
@@ -42,15 +44,29 @@ fn len(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
    }
    let arg: &Box<dyn Object> = args.get(0).unwrap();
 
+   // The length of a string is its number of chars (code points), byte_len gives its size in UTF-8
    if let Some(mky_string) = arg.as_any().downcast_ref::<MkyString>() {
-      return Box::new(Integer { value: mky_string.value.len() as i64 })
+      return Box::new(Integer { value: mky_string.value.chars().count() as i64 })
    } else if let Some(array) = arg.as_any().downcast_ref::<Array>() {
-      return Box::new(Integer { value: array.elements.len() as i64 })
+      return Box::new(Integer { value: array.elements.borrow().len() as i64 })
    }  else {
       return Box::new(Error::new(format!("argument to 'len' not supported, got {}", arg.r#type())))
    }
 } 
 
+fn byte_len(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
+   if args.len() != 1 {
+      return Box::new(Error::new(format!("wrong number of arguments. got={}, want=1", args.len())))
+   }
+   let arg: &Box<dyn Object> = args.first().unwrap();
+
+   if let Some(mky_string) = arg.as_any().downcast_ref::<MkyString>() {
+      return Box::new(Integer { value: mky_string.value.len() as i64 })
+   } else {
+      return Box::new(Error::new(format!("argument to 'byte_len' must be STRING, got {}", arg.r#type())))
+   }
+}
+
 fn first(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
    if args.len() != 1 {
       return Box::new(Error::new(format!("wrong number of arguments. got={}, want=1", args.len())))
@@ -58,8 +74,8 @@ fn first(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
    let arg: &Box<dyn Object> = args.get(0).unwrap();
    
    if let Some(arr) = arg.as_any().downcast_ref::<Array>() {
-      if !arr.elements.is_empty() {
-         return arr.elements.get(0).unwrap().clone()
+      if !arr.elements.borrow().is_empty() {
+         return arr.elements.borrow().first().unwrap().clone()
       } else {
          return Box::new(NULL)
       }
@@ -75,8 +91,8 @@ fn last(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
    let arg: &Box<dyn Object> = args.get(0).unwrap();
    
    if let Some(arr) = arg.as_any().downcast_ref::<Array>() {
-      if !arr.elements.is_empty() {
-         return arr.elements.get(arr.elements.len()-1).unwrap().clone()
+      if !arr.elements.borrow().is_empty() {
+         return arr.elements.borrow().last().unwrap().clone()
       } else {
          return Box::new(NULL)
       }
@@ -92,9 +108,9 @@ fn rest(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
    let arg: &Box<dyn Object> = args.get(0).unwrap();
    
    if let Some(arr) = arg.as_any().downcast_ref::<Array>() {
-      if !arr.elements.is_empty() {
-         let new_elements: Vec<Box<dyn Object>> = arr.elements.iter().skip(1).cloned().collect();
-         return Box::new(Array { elements: new_elements })
+      if !arr.elements.borrow().is_empty() {
+         let new_elements: Vec<Box<dyn Object>> = arr.elements.borrow().iter().skip(1).cloned().collect();
+         return Box::new(Array::new(new_elements))
       } else {
          return Box::new(NULL)
       }
@@ -110,9 +126,9 @@ fn push(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
    let arg: &Box<dyn Object> = args.get(0).unwrap();
    
    if let Some(arr) = arg.as_any().downcast_ref::<Array>() {
-      let mut new_elements: Vec<Box<dyn Object>> = arr.elements.iter().cloned().collect();
+      let mut new_elements: Vec<Box<dyn Object>> = arr.elements.borrow().iter().cloned().collect();
       new_elements.push(args.get(1).unwrap().clone());
-      return Box::new(Array { elements: new_elements })
+      return Box::new(Array::new(new_elements))
    } else {
       return Box::new(Error::new(format!("argument to 'push' must be ARRAY, got {}", arg.r#type())))
    }
@@ -135,11 +151,11 @@ fn append(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
 
    if let Some(array1) = arr1.as_any().downcast_ref::<Array>() {
       if let Some(array2) = arr2.as_any().downcast_ref::<Array>() {
-         let mut cloned_elements_1: Vec<Box<dyn Object>> = array1.elements.clone();
-         let mut cloned_elements_2: Vec<Box<dyn Object>> = array2.elements.clone();
+         let mut cloned_elements_1: Vec<Box<dyn Object>> = array1.elements.borrow().clone();
+         let mut cloned_elements_2: Vec<Box<dyn Object>> = array2.elements.borrow().clone();
          cloned_elements_1.append(&mut cloned_elements_2);
 
-         return Box::new(Array { elements: cloned_elements_1 })
+         return Box::new(Array::new(cloned_elements_1))
       } else {
          return Box::new(Error::new(format!("second argument to 'append' must be ARRAY, got {}", arr2.r#type())))
       }
@@ -158,7 +174,7 @@ fn insert(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
    let key_to_insert: &Box<dyn Object> = args.get(1).unwrap();
    let value_to_insert: &Box<dyn Object> = args.get(2).unwrap();
 
-   // Here we cannot mutate map.pairs because we cannot borrow data in a '&' reference as mutable
+   // insert leaves the original hash alone and returns an updated copy, use "hash[key] = value" to update it in place
    if let Some(map) = map_to_insert_into.as_any().downcast_ref::<Hash>() {
       if !key_to_insert.is_hashable() {
          return Box::new(Error::new(format!("second argument to 'insert' must be hashable, got {}", key_to_insert.r#type())))
@@ -166,10 +182,46 @@ fn insert(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
       let hash_key: HashKey = key_to_insert.downcast_hashable().unwrap().hash_key();
       let hash_pair: HashPair = HashPair { key: key_to_insert.clone(), value: value_to_insert.clone() };
 
-      let mut copied_map: HashMap<HashKey, HashPair> = map.pairs.clone();
+      let mut copied_map: HashMap<HashKey, HashPair> = map.pairs.borrow().clone();
       copied_map.insert(hash_key, hash_pair);
-      Box::new(Hash { pairs: copied_map })
+      Box::new(Hash::new(copied_map))
    } else {
       return Box::new(Error::new(format!("first argument to 'insert' must be HASH, got {}", map_to_insert_into.r#type())))
    }
 }
+
+// range(end), range(start, end) or range(start, end, step). Counts from start up to, but not including, end
+fn range(args: Vec<Box<dyn Object>>) -> Box<dyn Object> {
+   if args.is_empty() || args.len() > 3 {
+      return Box::new(Error::new(format!("wrong number of arguments. got={}, want=1 to 3", args.len())))
+   }
+
+   let mut bounds: Vec<i64> = vec![];
+   for arg in &args {
+      match arg.as_any().downcast_ref::<Integer>() {
+         Some(integer) => bounds.push(integer.value),
+         None => return Box::new(Error::new(format!("arguments to 'range' must be INTEGER, got {}", arg.r#type())))
+      }
+   }
+   let (start, end, step) = match bounds.as_slice() {
+      [end] => (0, *end, 1),
+      [start, end] => (*start, *end, 1),
+      [start, end, step] => (*start, *end, *step),
+      _ => unreachable!(),
+   };
+   if step == 0 {
+      return Box::new(Error::new(String::from("'range' step must not be zero")))
+   }
+
+   let mut elements: Vec<Box<dyn Object>> = vec![];
+   let mut current: i64 = start;
+   while (step > 0 && current < end) || (step < 0 && current > end) {
+      elements.push(Box::new(Integer { value: current }));
+      current = match current.checked_add(step) {
+         Some(next) => next,
+         None => break,
+      };
+   }
+
+   Box::new(Array::new(elements))
+}
@@ -2,9 +2,12 @@ pub mod builtins;
 
 use std::collections::HashMap;
 
-use crate::objects::environment::Environment;
-use crate::parser::ast::{Node, Program, IntegerLiteral, ExpressionStatement, Statement, Expression, Boolean, PrefixExpression, InfixExpression, BlockStatement, IfExpression, ReturnStatement, LetStatement, Identifier, FunctionLiteral, CallExpression, StringLiteral, ArrayLiteral, IndexExpression, HashLiteral};
-use crate::objects::{Object, Integer, Null, ObjectTypes, ReturnValue, Error, Function, MkyString, BuiltIn, Array, HashKey, HashPair, Hash};
+use std::rc::Rc;
+
+use crate::objects::environment::{Environment, Env};
+use crate::parser::ast::{Node, Program, IntegerLiteral, FloatLiteral, ExpressionStatement, Statement, Expression, Boolean, PrefixExpression, InfixExpression, AssignExpression, BlockStatement, IfExpression, ReturnStatement, LetStatement, Identifier, FunctionLiteral, CallExpression, StringLiteral, InterpolatedString, ArrayLiteral, IndexExpression, SliceExpression, ConditionalExpression, HashLiteral, NamedArgument, MatchExpression, Pattern, DestructuringLetStatement, WhileStatement, ForStatement, BreakStatement, ContinueStatement};
+use crate::objects::{Object, Integer, Float, Null, ObjectTypes, ReturnValue, Break, Continue, Error, Function, MkyString, BuiltIn, Array, HashKey, HashPair, Hash, Frame};
+use crate::lexer::token::Span;
 
 use self::builtins::lookup_builtins;
 
@@ -19,8 +22,22 @@ fn native_bool_to_boolean_object(input: bool) -> Box<dyn Object> {
    }
 }
 
+// Evaluate an AST Node, pinning any runtime error that doesn't know where it happened yet to the node's span.
+//    Since the innermost node returns first, errors end up pointing at the exact expression that failed
+pub fn eval(node: Box<&dyn Node>, env: &Env) -> Option<Box<dyn Object>> {
+   let span: Span = node.span();
+   let result: Option<Box<dyn Object>> = eval_node(*node, env);
+
+   if let Some(error) = result.as_ref().and_then(|obj| obj.as_any().downcast_ref::<Error>()) {
+      if error.span.is_none() {
+         return Some(Box::new(Error { span: Some(span), ..error.clone() }))
+      }
+   }
+   result
+}
+
 // Match the current AST Node being evaluated and evaluate it; returning an Object trait object
-pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Object>> {
+fn eval_node(node: &dyn Node, env: &Env) -> Option<Box<dyn Object>> {
 
    if node.node_as_any().is::<Program>() {
       let statements_to_eval: &Vec<Box<dyn Statement>> = &node.node_as_any().downcast_ref::<Program>().unwrap().statements;
@@ -43,6 +60,29 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
       if is_error(left.as_ref()) {
          return left
       }
+
+      // && and || short-circuit, the right side is only evaluated when it decides the result
+      let operator: &str = node_to_eval.operator.as_str();
+      if operator == "&&" || operator == "||" {
+         let left_truthy: bool = is_truthy(left.unwrap());
+         if (operator == "&&" && !left_truthy) || (operator == "||" && left_truthy) {
+            return Some(native_bool_to_boolean_object(left_truthy))
+         }
+         let right: Option<Box<dyn Object>> = eval(Box::new(node_to_eval.right.as_ref().unwrap().as_node()), env);
+         if is_error(right.as_ref()) {
+            return right
+         }
+         return Some(native_bool_to_boolean_object(is_truthy(right.unwrap())))
+      }
+
+      // ?? only falls back to the right side when the left one is null, unlike || which also skips false
+      if operator == "??" {
+         if !left.as_ref().unwrap().as_any().is::<Null>() {
+            return left
+         }
+         return eval(Box::new(node_to_eval.right.as_ref().unwrap().as_node()), env)
+      }
+
       let right: Option<Box<dyn Object>> = eval(Box::new(node_to_eval.right.as_ref().unwrap().as_node()), env);
       if is_error(right.as_ref()) {
          return right
@@ -50,9 +90,21 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
       return Some(eval_infix_expression(node_to_eval.operator.clone(), left.unwrap(), right.unwrap()))
    }
 
+   if node.node_as_any().is::<AssignExpression>() {
+      return eval_assign_expression(node.node_as_any().downcast_ref::<AssignExpression>().unwrap(), env)
+   }
+
    if node.node_as_any().is::<FunctionLiteral>() {
       let fn_node: &FunctionLiteral = node.node_as_any().downcast_ref::<FunctionLiteral>().unwrap();
-      return Some(Box::new(Function { params: fn_node.params.clone(), body: fn_node.body.clone(), env: env.clone()}));
+      return Some(Box::new(Function {
+         name: None,
+         params: fn_node.params.clone(),
+         defaults: fn_node.defaults.clone(),
+         rest: fn_node.rest.clone(),
+         body: fn_node.body.clone(),
+         span: fn_node.span,
+         env: Rc::clone(env),
+      }));
    }
 
    if node.node_as_any().is::<CallExpression>() {
@@ -62,11 +114,27 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
          return function
       }
 
-      let args: Vec<Box<dyn Object>> = eval_expressions(ce_node.arguments.as_ref().clone(), env);
+      // Named arguments are kept apart, the parser already made sure they come after the positional ones
+      let mut positional: Vec<Box<dyn Expression>> = vec![];
+      let mut named: Vec<(String, Box<dyn Object>)> = vec![];
+      for arg in ce_node.arguments.as_ref().unwrap() {
+         match arg.as_any().downcast_ref::<NamedArgument>() {
+            Some(named_arg) => {
+               let value: Option<Box<dyn Object>> = eval(Box::new(named_arg.value.as_node()), env);
+               if is_error(value.as_ref()) {
+                  return value
+               }
+               named.push((named_arg.name.value.clone(), value.unwrap()));
+            },
+            None => positional.push(arg.clone()),
+         }
+      }
+
+      let args: Vec<Box<dyn Object>> = eval_expressions(Some(&positional), env);
       if args.len() == 1 && is_error(args.get(0)) {
          return Some(args.get(0).unwrap().clone())
       }
-      return apply_function(function.unwrap(), args)
+      return apply_function(function.unwrap(), args, named, ce_node.span)
    }
 
    if node.node_as_any().is::<ExpressionStatement>() {
@@ -87,6 +155,10 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
       return Some(Box::new(MkyString { value: node_to_eval.value.clone() }))
    }
 
+   if node.node_as_any().is::<InterpolatedString>() {
+      return eval_interpolated_string(node.node_as_any().downcast_ref::<InterpolatedString>().unwrap(), env)
+   }
+
    if node.node_as_any().is::<ArrayLiteral>() {
       let node_to_eval: &ArrayLiteral = node.node_as_any().downcast_ref::<ArrayLiteral>().unwrap();
       let elements: Vec<Box<dyn Object>> = eval_expressions(Some(&node_to_eval.elements), env);
@@ -96,7 +168,7 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
          return Some(elements.get(0).unwrap().clone());
       }
 
-      return Some(Box::new(Array { elements }))
+      return Some(Box::new(Array::new(elements)))
    }
 
    if node.node_as_any().is::<LetStatement>() {
@@ -105,7 +177,32 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
       if is_error(value.as_ref()) {
          return value
       }
-      env.set(&node_to_eval.name.value, value.unwrap());
+
+      // Functions remember the first name they are bound to, so stack traces can say which function failed
+      let mut value: Box<dyn Object> = value.unwrap();
+      if let Some(func) = value.as_any().downcast_ref::<Function>() {
+         if func.name.is_none() {
+            value = Box::new(Function { name: Some(node_to_eval.name.value.clone()), ..func.clone() });
+         }
+      }
+      env.borrow_mut().set(&node_to_eval.name.value, value);
+   }
+
+   if node.node_as_any().is::<DestructuringLetStatement>() {
+      let node_to_eval: &DestructuringLetStatement = node.node_as_any().downcast_ref::<DestructuringLetStatement>().unwrap();
+      let value: Option<Box<dyn Object>> = eval(Box::new(node_to_eval.value.as_node()), env);
+      if is_error(value.as_ref()) {
+         return value
+      }
+
+      // Nothing is bound unless the whole pattern fits
+      let mut bindings: Vec<(String, Box<dyn Object>)> = vec![];
+      if let Err(message) = match_pattern(&node_to_eval.pattern, &value.unwrap(), env, &mut bindings) {
+         return Some(Box::new(Error::new(format!("cannot destructure: {}", message))))
+      }
+      for (name, value) in bindings {
+         env.borrow_mut().set(&name, value);
+      }
    }
 
    if node.node_as_any().is::<ReturnStatement>() {
@@ -117,6 +214,22 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
       return Some(Box::new(ReturnValue { value: value.unwrap() }))
    }
 
+   if node.node_as_any().is::<WhileStatement>() {
+      return eval_while_statement(node.node_as_any().downcast_ref::<WhileStatement>().unwrap(), env)
+   }
+
+   if node.node_as_any().is::<ForStatement>() {
+      return eval_for_statement(node.node_as_any().downcast_ref::<ForStatement>().unwrap(), env)
+   }
+
+   if node.node_as_any().is::<BreakStatement>() {
+      return Some(Box::new(Break))
+   }
+
+   if node.node_as_any().is::<ContinueStatement>() {
+      return Some(Box::new(Continue))
+   }
+
    if node.node_as_any().is::<BlockStatement>() {
       let statements_to_eval: &Vec<Box<dyn Statement>> = &node.node_as_any().downcast_ref::<BlockStatement>().unwrap().statements;
       return eval_block_statement(statements_to_eval, env)
@@ -127,6 +240,27 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
       return Some(eval_if_expression(&if_expr_to_eval, env))
    }
 
+   if node.node_as_any().is::<MatchExpression>() {
+      return eval_match_expression(node.node_as_any().downcast_ref::<MatchExpression>().unwrap(), env)
+   }
+
+   if node.node_as_any().is::<SliceExpression>() {
+      return eval_slice_expression(node.node_as_any().downcast_ref::<SliceExpression>().unwrap(), env)
+   }
+
+   if node.node_as_any().is::<ConditionalExpression>() {
+      let node_to_eval: &ConditionalExpression = node.node_as_any().downcast_ref::<ConditionalExpression>().unwrap();
+      let condition: Option<Box<dyn Object>> = eval(Box::new(node_to_eval.condition.as_node()), env);
+      if is_error(condition.as_ref()) {
+         return condition
+      }
+
+      if is_truthy(condition.unwrap()) {
+         return eval(Box::new(node_to_eval.consequence.as_node()), env)
+      }
+      return eval(Box::new(node_to_eval.alternative.as_node()), env)
+   }
+
    if node.node_as_any().is::<IndexExpression>() {
       let node_to_eval: &IndexExpression = node.node_as_any().downcast_ref::<IndexExpression>().unwrap();
 
@@ -134,6 +268,10 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
       if is_error(left.as_ref()) {
          return left
       }
+      // The index isn't evaluated at all when a?.[i] finds a null
+      if node_to_eval.optional && left.as_ref().unwrap().as_any().is::<Null>() {
+         return left
+      }
 
       let index: Option<Box<dyn Object>> = eval(Box::new(node_to_eval.index.as_node()), env);
       if is_error(index.as_ref()) {
@@ -149,6 +287,11 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
       return Some(native_bool_to_boolean_object(boolean_node.value))
    }
 
+   if node.node_as_any().is::<FloatLiteral>() {
+      let float_literal_node: &FloatLiteral = node.node_as_any().downcast_ref::<FloatLiteral>().unwrap();
+      return Some(Box::new(Float { value: float_literal_node.value }))
+   }
+
    if node.node_as_any().is::<IntegerLiteral>() {
       let integer_literal_node: &IntegerLiteral = node.node_as_any().downcast_ref::<IntegerLiteral>().unwrap();
       return Some(Box::new(Integer { value: integer_literal_node.value }))
@@ -166,7 +309,7 @@ pub fn eval(node: Box<&dyn Node>, env: &mut Environment) -> Option<Box<dyn Objec
 
 
 
-fn eval_program(stmts: &Vec<Box<dyn Statement>>, env: &mut Environment) -> Option<Box<dyn Object>> {
+fn eval_program(stmts: &Vec<Box<dyn Statement>>, env: &Env) -> Option<Box<dyn Object>> {
    let mut result: Option<Box<dyn Object>> = None;
 
    for stmt in stmts {
@@ -222,17 +365,25 @@ fn eval_bang_operator_expression(right: Box<dyn Object>) -> Box<dyn Object> {
 }
 
 fn eval_minus_prefix_expression(right: Box<dyn Object>) -> Box<dyn Object> {
+   if let Some(float) = right.as_any().downcast_ref::<Float>() {
+      return Box::new(Float { value: -float.value })
+   }
    if right.r#type() != ObjectTypes::IntegerObj.to_string() {
       return Box::new(Error::new(format!("unknown operator: -{}", right.r#type())))      
    } 
 
    let value: i64 = right.as_any().downcast_ref::<Integer>().unwrap().value;
-   Box::new(Integer { value: -value })
+   return match value.checked_neg() {
+      Some(negated) => Box::new(Integer { value: negated }),
+      None => Box::new(Error::new(format!("integer overflow: -({})", value)))
+   }
 }
 
 fn eval_infix_expression(operator: String, left: Box<dyn Object>, right: Box<dyn Object>) -> Box<dyn Object> {
    if left.r#type() == ObjectTypes::IntegerObj.to_string() && right.r#type() == ObjectTypes::IntegerObj.to_string() {
       return eval_integer_infix_expression(operator, left, right)
+   } else if number_as_f64(&left).is_some() && number_as_f64(&right).is_some() {
+      return eval_float_infix_expression(operator, left, right)
    } else if operator == "==" {
       return native_bool_to_boolean_object(left.inspect() == right.inspect())
    } else if operator == "!=" {
@@ -246,6 +397,89 @@ fn eval_infix_expression(operator: String, left: Box<dyn Object>, right: Box<dyn
    Box::new(Error::new(format!("unknown operator: {} {} {}", left.r#type(), operator, right.r#type())))
 }
 
+// Assigning evaluates to the new value, so "a = b = 1" sets both
+fn eval_assign_expression(node: &AssignExpression, env: &Env) -> Option<Box<dyn Object>> {
+   if let Some(target) = node.target.as_any().downcast_ref::<IndexExpression>() {
+      return eval_index_assignment(node, target, env)
+   }
+   let name: &str = &node.target.as_any().downcast_ref::<Identifier>().unwrap().value;
+
+   let value: Option<Box<dyn Object>> = eval(Box::new(node.value.as_node()), env);
+   if is_error(value.as_ref()) {
+      return value
+   }
+   let mut value: Box<dyn Object> = value.unwrap();
+
+   // "x += 1" is "x = x + 1"
+   if node.operator != "=" {
+      let current: Box<dyn Object> = match env.borrow().get(name) {
+         Some(current) => current,
+         None => return Some(Box::new(Error::new(format!("identifier not found: {}", name))))
+      };
+      value = eval_infix_expression(node.operator.trim_end_matches('=').to_string(), current, value);
+      if is_error(Some(&value)) {
+         return Some(value)
+      }
+   }
+
+   if !env.borrow_mut().assign(name, value.clone()) {
+      return Some(Box::new(Error::new(format!("cannot assign to undefined variable: {}", name))))
+   }
+   Some(value)
+}
+
+// "arr[i] = v" and "hash[k] = v" update the array or hash in place, every binding sharing it sees the change
+fn eval_index_assignment(node: &AssignExpression, target: &IndexExpression, env: &Env) -> Option<Box<dyn Object>> {
+   let left: Option<Box<dyn Object>> = eval(Box::new(target.left.as_node()), env);
+   if is_error(left.as_ref()) {
+      return left
+   }
+   let index: Option<Box<dyn Object>> = eval(Box::new(target.index.as_node()), env);
+   if is_error(index.as_ref()) {
+      return index
+   }
+   let value: Option<Box<dyn Object>> = eval(Box::new(node.value.as_node()), env);
+   if is_error(value.as_ref()) {
+      return value
+   }
+   let (left, index, mut value) = (left.unwrap(), index.unwrap(), value.unwrap());
+
+   if node.operator != "=" {
+      let current: Box<dyn Object> = eval_index_expression(left.clone(), index.clone());
+      if is_error(Some(&current)) {
+         return Some(current)
+      }
+      value = eval_infix_expression(node.operator.trim_end_matches('=').to_string(), current, value);
+      if is_error(Some(&value)) {
+         return Some(value)
+      }
+   }
+
+   if let Some(array) = left.as_any().downcast_ref::<Array>() {
+      let idx: i64 = match index.as_any().downcast_ref::<Integer>() {
+         Some(integer) => integer.value,
+         None => return Some(Box::new(Error::new(format!("array index must be INTEGER, got {}", index.r#type()))))
+      };
+      // Negative indexes count from the end, like they do when reading
+      let len: usize = array.elements.borrow().len();
+      let position: usize = match resolve_index(idx, len) {
+         Some(position) => position,
+         None => return Some(Box::new(Error::new(format!("index out of bounds: {} (length {})", idx, len))))
+      };
+      array.elements.borrow_mut()[position] = value.clone();
+   } else if let Some(hash) = left.as_any().downcast_ref::<Hash>() {
+      if !index.is_hashable() {
+         return Some(Box::new(Error::new(format!("unusable as hash key: {}", index.r#type()))))
+      }
+      let hash_key: HashKey = index.downcast_hashable().unwrap().hash_key();
+      hash.pairs.borrow_mut().insert(hash_key, HashPair { key: index, value: value.clone() });
+   } else {
+      return Some(Box::new(Error::new(format!("index assignment not supported: {}", left.r#type()))))
+   }
+
+   Some(value)
+}
+
 fn eval_string_infix_expression(operator: String, left: Box<dyn Object>, right: Box<dyn Object>) -> Box<dyn Object> {
    if operator != "+" {
       return Box::new(Error::new(format!("unknown operator: {} {} {}", left.r#type(), operator, right.r#type())))
@@ -259,14 +493,36 @@ fn eval_integer_infix_expression(operator: String, left: Box<dyn Object>, right:
    let left_val: i64 = left.as_any().downcast_ref::<Integer>().unwrap().value;
    let right_val: i64 = right.as_any().downcast_ref::<Integer>().unwrap().value;
 
+   // Arithmetic is checked: overflowing or dividing by zero is a Monkey error, not a crash of the interpreter
+   let checked: Option<i64> = match operator.as_str() {
+      "+" => left_val.checked_add(right_val),
+      "-" => left_val.checked_sub(right_val),
+      "*" => left_val.checked_mul(right_val),
+      "/" => {
+         if right_val == 0 {
+            return Box::new(Error::new(format!("division by zero: {} / {}", left_val, right_val)))
+         }
+         left_val.checked_div(right_val)
+      },
+      "%" => {
+         if right_val == 0 {
+            return Box::new(Error::new(format!("division by zero: {} % {}", left_val, right_val)))
+         }
+         left_val.checked_rem(right_val)
+      },
+      _ => None,
+   };
+
    return match operator.as_str() {
-      "+" => Box::new(Integer { value: left_val + right_val }),
-      "-" => Box::new(Integer { value: left_val - right_val }),
-      "*" => Box::new(Integer { value: left_val * right_val }),
-      "/" => Box::new(Integer { value: left_val / right_val }),
+      "+" | "-" | "*" | "/" | "%" => match checked {
+         Some(value) => Box::new(Integer { value }),
+         None => Box::new(Error::new(format!("integer overflow: {} {} {}", left_val, operator, right_val)))
+      },
 
       "<" => native_bool_to_boolean_object(left_val < right_val),
       ">" => native_bool_to_boolean_object(left_val > right_val),
+      "<=" => native_bool_to_boolean_object(left_val <= right_val),
+      ">=" => native_bool_to_boolean_object(left_val >= right_val),
       "==" => native_bool_to_boolean_object(left_val == right_val),
       "!=" => native_bool_to_boolean_object(left_val != right_val),
  
@@ -274,7 +530,60 @@ fn eval_integer_infix_expression(operator: String, left: Box<dyn Object>, right:
    }
 }
 
-fn eval_if_expression(if_expr: &IfExpression, env: &mut Environment) -> Box<dyn Object> {
+// At least one side is a FLOAT, so the INTEGER side (if any) gets promoted
+fn eval_float_infix_expression(operator: String, left: Box<dyn Object>, right: Box<dyn Object>) -> Box<dyn Object> {
+   let left_val: f64 = number_as_f64(&left).unwrap();
+   let right_val: f64 = number_as_f64(&right).unwrap();
+
+   return match operator.as_str() {
+      // Same policy as for integers, rather than quietly producing inf or NaN
+      "+" | "-" | "*" => {
+         let value: f64 = match operator.as_str() {
+            "+" => left_val + right_val,
+            "-" => left_val - right_val,
+            _ => left_val * right_val,
+         };
+         if !value.is_finite() {
+            return Box::new(Error::new(format!("float overflow: {:?} {} {:?}", left_val, operator, right_val)))
+         }
+         Box::new(Float { value })
+      },
+      "/" => {
+         if right_val == 0.0 {
+            return Box::new(Error::new(format!("division by zero: {:?} / {:?}", left_val, right_val)))
+         }
+         Box::new(Float { value: left_val / right_val })
+      },
+      "%" => {
+         if right_val == 0.0 {
+            return Box::new(Error::new(format!("division by zero: {:?} % {:?}", left_val, right_val)))
+         }
+         Box::new(Float { value: left_val % right_val })
+      },
+
+      "<" => native_bool_to_boolean_object(left_val < right_val),
+      ">" => native_bool_to_boolean_object(left_val > right_val),
+      "<=" => native_bool_to_boolean_object(left_val <= right_val),
+      ">=" => native_bool_to_boolean_object(left_val >= right_val),
+      "==" => native_bool_to_boolean_object(left_val == right_val),
+      "!=" => native_bool_to_boolean_object(left_val != right_val),
+
+      _ => Box::new(Error::new(format!("unknown operator: {} {} {}", left.r#type(), operator, right.r#type())))
+   }
+}
+
+// The value of an INTEGER or FLOAT as an f64, None for any other object
+fn number_as_f64(obj: &Box<dyn Object>) -> Option<f64> {
+   if let Some(integer) = obj.as_any().downcast_ref::<Integer>() {
+      return Some(integer.value as f64)
+   }
+   if let Some(float) = obj.as_any().downcast_ref::<Float>() {
+      return Some(float.value)
+   }
+   None
+}
+
+fn eval_if_expression(if_expr: &IfExpression, env: &Env) -> Box<dyn Object> {
    let condition: Option<Box<dyn Object>> = eval(Box::new(if_expr.condition.as_ref().unwrap().as_node()), env);
    if is_error(condition.as_ref()) {
       return condition.unwrap()
@@ -289,6 +598,210 @@ fn eval_if_expression(if_expr: &IfExpression, env: &mut Environment) -> Box<dyn
    }
 }
 
+// The loop runs in the enclosing environment, just like the blocks of an if. Like an if without an else, it evaluates to null
+fn eval_while_statement(while_stmt: &WhileStatement, env: &Env) -> Option<Box<dyn Object>> {
+   loop {
+      let condition: Option<Box<dyn Object>> = eval(Box::new(while_stmt.condition.as_node()), env);
+      if is_error(condition.as_ref()) {
+         return condition
+      }
+      if !is_truthy(condition.unwrap()) {
+         return Some(Box::new(NULL))
+      }
+
+      let result: Option<Box<dyn Object>> = eval_block_statement(&while_stmt.body.statements, env);
+      if let Some(obj) = result.as_ref() {
+         if obj.as_any().is::<Break>() {
+            return Some(Box::new(NULL))
+         }
+         if obj.as_any().is::<ReturnValue>() || obj.as_any().is::<Error>() {
+            return result
+         }
+      }
+   }
+}
+
+// Every iteration gets its own scope holding the loop variables, so a closure made in the body keeps the 
+//    values of the iteration it was made in. Evaluates to null, the same as a while loop
+fn eval_for_statement(for_stmt: &ForStatement, env: &Env) -> Option<Box<dyn Object>> {
+   let iterable: Option<Box<dyn Object>> = eval(Box::new(for_stmt.iterable.as_node()), env);
+   if is_error(iterable.as_ref()) {
+      return iterable
+   }
+   let iterable: Box<dyn Object> = iterable.unwrap();
+
+   // (index, element) for arrays and strings, (key, value) for hashes
+   let mut items: Vec<(Box<dyn Object>, Box<dyn Object>)> = vec![];
+   if let Some(array) = iterable.as_any().downcast_ref::<Array>() {
+      for (i, element) in array.elements.borrow().iter().enumerate() {
+         items.push((Box::new(Integer { value: i as i64 }), element.clone()));
+      }
+   } else if let Some(string) = iterable.as_any().downcast_ref::<MkyString>() {
+      for (i, ch) in string.value.chars().enumerate() {
+         items.push((Box::new(Integer { value: i as i64 }), Box::new(MkyString { value: ch.to_string() })));
+      }
+   } else if let Some(hash) = iterable.as_any().downcast_ref::<Hash>() {
+      for pair in hash.pairs.borrow().values() {
+         items.push((pair.key.clone(), pair.value.clone()));
+      }
+   } else {
+      return Some(Box::new(Error::new(format!("cannot iterate over {}", iterable.r#type()))))
+   }
+   let lone_variable_gets_key: bool = iterable.as_any().is::<Hash>();
+
+   for (key, value) in items {
+      let iteration_env: Env = Environment::new_enclosed_env(env);
+      match (for_stmt.variables.first(), for_stmt.variables.get(1)) {
+         (Some(first), Some(second)) => {
+            iteration_env.borrow_mut().set(&first.value, key);
+            iteration_env.borrow_mut().set(&second.value, value);
+         },
+         (Some(only), None) => {
+            iteration_env.borrow_mut().set(&only.value, if lone_variable_gets_key { key } else { value });
+         },
+         _ => {},
+      }
+
+      let result: Option<Box<dyn Object>> = eval_block_statement(&for_stmt.body.statements, &iteration_env);
+      if let Some(obj) = result.as_ref() {
+         if obj.as_any().is::<Break>() {
+            return Some(Box::new(NULL))
+         }
+         if obj.as_any().is::<ReturnValue>() || obj.as_any().is::<Error>() {
+            return result
+         }
+      }
+   }
+
+   Some(Box::new(NULL))
+}
+
+// Arms are tried top to bottom, the first one whose pattern matches (and whose guard holds) wins.
+//    Bindings live in a scope of their own so they don't leak out of the arm
+fn eval_match_expression(match_expr: &MatchExpression, env: &Env) -> Option<Box<dyn Object>> {
+   let subject: Option<Box<dyn Object>> = eval(Box::new(match_expr.subject.as_node()), env);
+   if is_error(subject.as_ref()) {
+      return subject
+   }
+   let subject: Box<dyn Object> = subject.unwrap();
+
+   for arm in &match_expr.arms {
+      let mut bindings: Vec<(String, Box<dyn Object>)> = vec![];
+      if match_pattern(&arm.pattern, &subject, env, &mut bindings).is_err() {
+         continue;
+      }
+
+      let arm_env: Env = Environment::new_enclosed_env(env);
+      for (name, value) in bindings {
+         arm_env.borrow_mut().set(&name, value);
+      }
+
+      if let Some(guard) = arm.guard.as_ref() {
+         let condition: Option<Box<dyn Object>> = eval(Box::new(guard.as_node()), &arm_env);
+         if is_error(condition.as_ref()) {
+            return condition
+         }
+         if !is_truthy(condition.unwrap()) {
+            continue;
+         }
+      }
+
+      // An empty arm body has no value, the match evaluates to null then
+      return eval_block_statement(&arm.body.statements, &arm_env).or(Some(Box::new(NULL)))
+   }
+
+   Some(Box::new(NULL))
+}
+
+// Checks `value` against `pattern`, collecting the names the pattern binds along the way.
+//    The error says why the value doesn't fit, a destructuring let reports it while a match just tries the next arm
+fn match_pattern(pattern: &Pattern, value: &Box<dyn Object>, env: &Env, bindings: &mut Vec<(String, Box<dyn Object>)>) -> Result<(), String> {
+   return match pattern {
+      Pattern::Wildcard(_) => Ok(()),
+      Pattern::Binding(identifier) => {
+         bindings.push((identifier.value.clone(), value.clone()));
+         Ok(())
+      },
+      Pattern::Literal(literal) => {
+         match eval(Box::new(literal.as_node()), env) {
+            Some(expected) if values_equal(&expected, value) => Ok(()),
+            _ => Err(format!("expected {}, got {}", literal.string(), value.inspect()))
+         }
+      },
+      Pattern::Or(alternatives) => {
+         for alternative in alternatives {
+            let mut alternative_bindings: Vec<(String, Box<dyn Object>)> = vec![];
+            if match_pattern(alternative, value, env, &mut alternative_bindings).is_ok() {
+               bindings.append(&mut alternative_bindings);
+               return Ok(())
+            }
+         }
+         Err(format!("expected {}, got {}", pattern.string(), value.inspect()))
+      },
+      Pattern::Array { elements, rest, .. } => {
+         let array: &Array = match value.as_any().downcast_ref::<Array>() {
+            Some(array) => array,
+            None => return Err(format!("expected an array, got {}", value.r#type()))
+         };
+         let array_elements: Vec<Box<dyn Object>> = array.elements.borrow().clone();
+
+         // Without a rest the lengths have to line up exactly
+         if rest.is_none() && array_elements.len() != elements.len() {
+            return Err(format!("expected an array of {} elements, got {}", elements.len(), array_elements.len()))
+         }
+         if array_elements.len() < elements.len() {
+            return Err(format!("expected an array of at least {} elements, got {}", elements.len(), array_elements.len()))
+         }
+         for (element_pattern, element) in elements.iter().zip(array_elements.iter()) {
+            match_pattern(element_pattern, element, env, bindings)?;
+         }
+         if let Some(rest) = rest {
+            let remaining: Box<dyn Object> = Box::new(Array::new(array_elements[elements.len()..].to_vec()));
+            return match_pattern(rest, &remaining, env, bindings)
+         }
+         Ok(())
+      },
+      Pattern::Hash { pairs, .. } => {
+         let hash: &Hash = match value.as_any().downcast_ref::<Hash>() {
+            Some(hash) => hash,
+            None => return Err(format!("expected a hash, got {}", value.r#type()))
+         };
+
+         // Keys that aren't mentioned in the pattern are ignored
+         for (key_node, value_pattern) in pairs {
+            let key: Box<dyn Object> = match eval(Box::new(key_node.as_node()), env) {
+               Some(key) if key.is_hashable() => key,
+               _ => return Err(format!("unusable as hash key: {}", key_node.string()))
+            };
+            let entry: Option<Box<dyn Object>> = hash.pairs.borrow().get(&key.downcast_hashable().unwrap().hash_key()).map(|pair| pair.value.clone());
+            match entry {
+               Some(entry) => match_pattern(value_pattern, &entry, env, bindings)?,
+               None => return Err(format!("missing hash key: {}", key_node.string()))
+            }
+         }
+         Ok(())
+      },
+   }
+}
+
+// Structural equality for literal patterns. Integers and floats compare by value, so `1` matches `1.0`
+fn values_equal(left: &Box<dyn Object>, right: &Box<dyn Object>) -> bool {
+   if let (Some(left_num), Some(right_num)) = (number_as_f64(left), number_as_f64(right)) {
+      if left.as_any().is::<Integer>() && right.as_any().is::<Integer>() {
+         return left.as_any().downcast_ref::<Integer>().unwrap().value == right.as_any().downcast_ref::<Integer>().unwrap().value
+      }
+      return left_num == right_num
+   }
+   if let (Some(left_str), Some(right_str)) = (left.as_any().downcast_ref::<MkyString>(), right.as_any().downcast_ref::<MkyString>()) {
+      return left_str.value == right_str.value
+   }
+   if let (Some(left_bool), Some(right_bool)) = (left.as_any().downcast_ref::<crate::objects::Boolean>(), right.as_any().downcast_ref::<crate::objects::Boolean>()) {
+      return left_bool.value == right_bool.value
+   }
+
+   left.as_any().is::<Null>() && right.as_any().is::<Null>()
+}
+
 fn is_truthy(condition: Box<dyn Object>) -> bool {
    if condition.as_any().is::<Null>() {
       return false
@@ -301,7 +814,7 @@ fn is_truthy(condition: Box<dyn Object>) -> bool {
    true
 }
 
-fn eval_block_statement(stmts: &Vec<Box<dyn Statement>>, env: &mut Environment) -> Option<Box<dyn Object>> {
+fn eval_block_statement(stmts: &Vec<Box<dyn Statement>>, env: &Env) -> Option<Box<dyn Object>> {
    let mut result: Option<Box<dyn Object>> = None;
 
    for stmt in stmts {
@@ -312,6 +825,10 @@ fn eval_block_statement(stmts: &Vec<Box<dyn Statement>>, env: &mut Environment)
          if result.as_ref().unwrap().as_any().is::<ReturnValue>() {
             return result
          } 
+         // Loop signals unwind through nested blocks (like an if inside the loop) up to eval_while_statement
+         if result.as_ref().unwrap().as_any().is::<Break>() || result.as_ref().unwrap().as_any().is::<Continue>() {
+            return result
+         }
          if result.as_ref().unwrap().as_any().is::<Error>() {
             return result
          }
@@ -328,9 +845,9 @@ fn is_error(obj: Option<&Box<dyn Object>>) -> bool {
    false
 }
 
-fn eval_identifier(node: &Identifier, env: &Environment) -> Option<Box<dyn Object>> {
-   return match env.get(&node.value) {
-      Some(object) => Some(object.clone()),
+fn eval_identifier(node: &Identifier, env: &Env) -> Option<Box<dyn Object>> {
+   return match env.borrow().get(&node.value) {
+      Some(object) => Some(object),
 
       None => {
          match lookup_builtins(&node.value) {
@@ -341,7 +858,7 @@ fn eval_identifier(node: &Identifier, env: &Environment) -> Option<Box<dyn Objec
    }
 }
 
-fn eval_expressions(exps: Option<&Vec<Box<dyn Expression>>>, env: &mut Environment) -> Vec<Box<dyn Object>> {
+fn eval_expressions(exps: Option<&Vec<Box<dyn Expression>>>, env: &Env) -> Vec<Box<dyn Object>> {
    let mut result: Vec<Box<dyn Object>> = vec![];
    match exps {
       Some(exprs) => {
@@ -359,26 +876,89 @@ fn eval_expressions(exps: Option<&Vec<Box<dyn Expression>>>, env: &mut Environme
    result
 }
 
-fn apply_function(function: Box<dyn Object>, args: Vec<Box<dyn Object>>) -> Option<Box<dyn Object>> {
+fn apply_function(function: Box<dyn Object>, args: Vec<Box<dyn Object>>, named: Vec<(String, Box<dyn Object>)>, call_site: Span) -> Option<Box<dyn Object>> {
    if let Some(func) = function.as_any().downcast_ref::<Function>() {
-      let mut extended_env: Environment = extend_function_env(func, args);
-      let eval: Option<Box<dyn Object>> = eval(Box::new(func.body.as_ref().unwrap().as_node()), &mut extended_env);
+      let extended_env: Env = match extend_function_env(func, args, named) {
+         Ok(env) => env,
+         Err(error) => return Some(error)
+      };
+      let eval: Option<Box<dyn Object>> = eval(Box::new(func.body.as_ref().unwrap().as_node()), &extended_env);
+
+      // An error unwinding out of this call gets this call added to its stack trace
+      if let Some(error) = eval.as_ref().and_then(|obj| obj.as_any().downcast_ref::<Error>()) {
+         let mut error: Error = error.clone();
+         error.stack.push(Frame { name: func.name.clone(), call_site });
+         return Some(Box::new(error))
+      }
 
       return unwrap_return_value(eval);
    } else if let Some(builtin_func) = function.as_any().downcast_ref::<BuiltIn>() {
+      if !named.is_empty() {
+         return Some(Box::new(Error::new(format!("builtin functions don't take named arguments, got: {}", named.first().unwrap().0))))
+      }
       return Some((builtin_func.func)(args))
    } else {
       return Some(Box::new(Error::new(format!("not a function: {}", function.r#type()))))  
    }
 }
 
-fn extend_function_env(function: &Function, args: Vec<Box<dyn Object>>) -> Environment {
-   let mut env: Environment = Environment::new_enclosed_env(function.env.clone());
-   for (param_idx, param) in function.params.as_ref().unwrap().iter().enumerate() {
-     env.set(&param.value, args.get(param_idx).unwrap().clone());
+// Parameters are filled from the positional arguments first, then from the named ones, then from their defaults.
+//    A default is evaluated in the new environment, so it can use the parameters before it: fn(x, y = x * 2)
+fn extend_function_env(function: &Function, args: Vec<Box<dyn Object>>, named: Vec<(String, Box<dyn Object>)>) -> Result<Env, Box<dyn Object>> {
+   let params: &Vec<Identifier> = function.params.as_ref().unwrap();
+   let name: &str = function.name.as_deref().unwrap_or("fn");
+   let arity_error = |message: String| -> Box<dyn Object> {
+      Box::new(Error::new(format!("{} ({} defined at {})", message, name, function.span)))
+   };
+
+   let required: usize = params.iter().filter(|p| !function.defaults.contains_key(&p.value)).count();
+   if (args.len() > params.len() && function.rest.is_none()) || (args.len() < required && named.is_empty()) {
+      let want: String = if function.rest.is_some() {
+         format!("at least {}", required)
+      } else if required != params.len() {
+         format!("{} to {}", required, params.len())
+      } else {
+         format!("{}", params.len())
+      };
+      return Err(arity_error(format!("wrong number of arguments: want={}, got={}", want, args.len())))
    }
 
-   env
+   let mut named_values: HashMap<String, Box<dyn Object>> = HashMap::new();
+   for (arg_name, value) in named {
+      let position: Option<usize> = params.iter().position(|p| p.value == arg_name);
+      match position {
+         None => return Err(arity_error(format!("unexpected named argument: {}", arg_name))),
+         Some(idx) if idx < args.len() || named_values.contains_key(&arg_name) => {
+            return Err(arity_error(format!("multiple values for argument: {}", arg_name)))
+         },
+         Some(_) => { named_values.insert(arg_name, value); },
+      }
+   }
+
+   let env: Env = Environment::new_enclosed_env(&function.env);
+   for (param_idx, param) in params.iter().enumerate() {
+      let value: Box<dyn Object> = if let Some(arg) = args.get(param_idx) {
+         arg.clone()
+      } else if let Some(value) = named_values.remove(&param.value) {
+         value
+      } else if let Some(default) = function.defaults.get(&param.value) {
+         let value: Option<Box<dyn Object>> = eval(Box::new(default.as_node()), &env);
+         if is_error(value.as_ref()) {
+            return Err(value.unwrap())
+         }
+         value.unwrap()
+      } else {
+         return Err(arity_error(format!("missing argument for parameter: {}", param.value)))
+      };
+      env.borrow_mut().set(&param.value, value);
+   }
+
+   if let Some(rest) = function.rest.as_ref() {
+      let remaining: Vec<Box<dyn Object>> = args.iter().skip(params.len()).cloned().collect();
+      env.borrow_mut().set(&rest.value, Box::new(Array::new(remaining)));
+   }
+
+   Ok(env)
 }
 
 fn unwrap_return_value(obj: Option<Box<dyn Object>>) -> Option<Box<dyn Object>> {
@@ -398,6 +978,8 @@ fn unwrap_return_value(obj: Option<Box<dyn Object>>) -> Option<Box<dyn Object>>
 fn eval_index_expression(left: Box<dyn Object>, index: Box<dyn Object>) -> Box<dyn Object> {
    if left.r#type() == ObjectTypes::ArrayObj.to_string() && index.r#type() == ObjectTypes::IntegerObj.to_string() {
       return eval_array_index_expression(left, index)
+   } else if left.r#type() == ObjectTypes::StringObj.to_string() && index.r#type() == ObjectTypes::IntegerObj.to_string() {
+      return eval_string_index_expression(left, index)
    } else if left.r#type() == ObjectTypes::HashObj.to_string() {
       return eval_hash_index_expression(left, index)
    } else {
@@ -409,12 +991,80 @@ fn eval_array_index_expression(array: Box<dyn Object>, index: Box<dyn Object>) -
    let array_obj: &Array = array.as_any().downcast_ref::<Array>().unwrap();
    let idx: i64 = index.as_any().downcast_ref::<Integer>().unwrap().value.clone();
 
-   let max: i64 = array_obj.elements.len() as i64 - 1;
-   if idx < 0 || idx > max {
-      return Box::new(NULL)
+   return match resolve_index(idx, array_obj.elements.borrow().len()) {
+      Some(idx) => array_obj.elements.borrow().get(idx).unwrap().clone(),
+      None => Box::new(NULL)
+   }
+}
+
+// Strings are indexed by character, not by byte, the same way len counts them
+fn eval_string_index_expression(string: Box<dyn Object>, index: Box<dyn Object>) -> Box<dyn Object> {
+   let chars: Vec<char> = string.as_any().downcast_ref::<MkyString>().unwrap().value.chars().collect();
+   let idx: i64 = index.as_any().downcast_ref::<Integer>().unwrap().value;
+
+   return match resolve_index(idx, chars.len()) {
+      Some(idx) => Box::new(MkyString { value: chars[idx].to_string() }),
+      None => Box::new(NULL)
+   }
+}
+
+// A negative index counts from the end, -1 being the last element. None when it's out of range either way
+fn resolve_index(idx: i64, len: usize) -> Option<usize> {
+   let resolved: i64 = if idx < 0 { idx + len as i64 } else { idx };
+   if resolved < 0 || resolved >= len as i64 {
+      return None
+   }
+
+   Some(resolved as usize)
+}
+
+// Bounds work like in Python: negative ones count from the end, out of range ones get clamped to the ends,
+//    and a start past the end gives an empty result instead of an error
+fn eval_slice_expression(node: &SliceExpression, env: &Env) -> Option<Box<dyn Object>> {
+   let left: Option<Box<dyn Object>> = eval(Box::new(node.left.as_node()), env);
+   if is_error(left.as_ref()) {
+      return left
+   }
+   let left: Box<dyn Object> = left.unwrap();
+   if node.optional && left.as_any().is::<Null>() {
+      return Some(left)
    }
 
-   return array_obj.elements.get(idx as usize).unwrap().clone()
+   let len: usize = if let Some(array) = left.as_any().downcast_ref::<Array>() {
+      array.elements.borrow().len()
+   } else if let Some(string) = left.as_any().downcast_ref::<MkyString>() {
+      string.value.chars().count()
+   } else {
+      return Some(Box::new(Error::new(format!("slice operator not supported: {}", left.r#type()))))
+   };
+
+   let mut bounds: Vec<usize> = vec![];
+   for (bound, default) in [(&node.start, 0), (&node.end, len)] {
+      let bound: Option<Box<dyn Object>> = match bound {
+         Some(expr) => eval(Box::new(expr.as_node()), env),
+         None => {
+            bounds.push(default);
+            continue;
+         },
+      };
+      if is_error(bound.as_ref()) {
+         return bound
+      }
+      let bound: Box<dyn Object> = bound.unwrap();
+      let value: i64 = match bound.as_any().downcast_ref::<Integer>() {
+         Some(integer) => integer.value,
+         None => return Some(Box::new(Error::new(format!("slice bounds must be INTEGER, got {}", bound.r#type()))))
+      };
+      let resolved: i64 = if value < 0 { value + len as i64 } else { value };
+      bounds.push(resolved.clamp(0, len as i64) as usize);
+   }
+   let (start, end) = (bounds[0], bounds[1].max(bounds[0]));
+
+   if let Some(array) = left.as_any().downcast_ref::<Array>() {
+      return Some(Box::new(Array::new(array.elements.borrow()[start..end].to_vec())))
+   }
+   let value: String = left.as_any().downcast_ref::<MkyString>().unwrap().value.chars().skip(start).take(end - start).collect();
+   Some(Box::new(MkyString { value }))
 }
 
 fn eval_hash_index_expression(hash: Box<dyn Object>, index: Box<dyn Object>) -> Box<dyn Object> {
@@ -424,13 +1074,31 @@ fn eval_hash_index_expression(hash: Box<dyn Object>, index: Box<dyn Object>) ->
       return Box::new(Error::new(format!("unusable as hash key: {}", index.r#type())))
    }
 
-   return match hash_obj.pairs.get(&index.downcast_hashable().unwrap().hash_key()) {
+   return match hash_obj.pairs.borrow().get(&index.downcast_hashable().unwrap().hash_key()) {
       Some(hp) => hp.value.clone(),
       None => Box::new(NULL)
    }
 }
 
-fn eval_hash_literal(node: &HashLiteral, env: &mut Environment) -> Option<Box<dyn Object>> {
+// Every embedded expression is shown the same way print would show it
+fn eval_interpolated_string(node: &InterpolatedString, env: &Env) -> Option<Box<dyn Object>> {
+   let values: Vec<Box<dyn Object>> = eval_expressions(Some(&node.expressions), env);
+   if values.len() == 1 && is_error(values.first()) {
+      return Some(values.first().unwrap().clone());
+   }
+
+   let mut value: String = String::new();
+   for (i, string) in node.strings.iter().enumerate() {
+      value.push_str(string.as_str());
+      if let Some(obj) = values.get(i) {
+         value.push_str(obj.inspect().as_str());
+      }
+   }
+
+   Some(Box::new(MkyString { value }))
+}
+
+fn eval_hash_literal(node: &HashLiteral, env: &Env) -> Option<Box<dyn Object>> {
    let mut pairs: HashMap<HashKey, HashPair> = HashMap::new();
 
    for (key_node, value_node) in &node.pairs {
@@ -453,5 +1121,5 @@ fn eval_hash_literal(node: &HashLiteral, env: &mut Environment) -> Option<Box<dy
       pairs.insert(hashed, HashPair { key, value: value.unwrap() });
    }
 
-   return Some(Box::new(Hash { pairs }))
+   return Some(Box::new(Hash::new(pairs)))
 }
@@ -1,7 +1,8 @@
 pub mod token;
 
 use color_eyre::eyre::Result;
-use token::{Token, TokenType, lookup};
+use token::{Token, TokenType, Span, lookup};
+use crate::parser::errors::ParseError;
 
 #[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
@@ -10,11 +11,19 @@ pub struct Lexer {
    position: usize,
    read_position: usize,
    ch: char,
+   // Line and column of the current char, used to build the spans of tokens
+   line: usize,
+   column: usize,
+   // Errors found while lexing, the parser picks these up as it reads tokens
+   pub errors: Vec<ParseError>,
+   // One entry per `${` we are inside of, counting the '{' opened in it so we know which '}' closes it
+   templates: Vec<usize>,
 }
 impl Lexer {
    pub fn new(input: String) -> Self {
       let mut l: Lexer = Lexer {
          input,
+         line: 1,
          ..Default::default()
       };
       l.read_char();
@@ -23,43 +32,95 @@ impl Lexer {
    }
 
    fn read_char(&mut self) {
-      if self.read_position >= self.input.len() {
-         self.ch = '\0';
+      if self.ch == '\n' {
+         self.line += 1;
+         self.column = 1;
       } else {
-         self.ch = self.input.as_bytes()[self.read_position].into();
+         self.column += 1;
       }
+
+      // Positions are byte offsets, so a multi-byte char moves read_position by its UTF-8 length
       self.position = self.read_position;
-      self.read_position += 1;
+      match self.input.get(self.read_position..).and_then(|rest| rest.chars().next()) {
+         Some(ch) => {
+            self.ch = ch;
+            self.read_position += ch.len_utf8();
+         },
+         None => {
+            self.ch = '\0';
+            self.read_position += 1;
+         }
+      }
    }
 
-   fn peek_char(&mut self) -> char {
-      if self.read_position >= self.input.len() {
-         return '\0'
-      } else {
-         return self.input.as_bytes()[self.read_position].into();
-      }
+   fn peek_char(&self) -> char {
+      self.peek_char_at(0)
+   }
+
+   // Looks `offset` chars past the next one without consuming anything
+   fn peek_char_at(&self, offset: usize) -> char {
+      self.input.get(self.read_position..).and_then(|rest| rest.chars().nth(offset)).unwrap_or('\0')
    }
 
    fn read_ident(&mut self) -> &str {
       let pos: usize = self.position;
-      while self.is_letter(self.ch) {
+      while self.is_ident_continue(self.ch) {
          self.read_char()
       }
 
       self.input[pos..self.position].into()
    }
 
-   fn read_num(&mut self) -> &str {
+   // Reads an integer like "42" or a float like "3.14", "1e-9" or "2.5E3". Returns the literal and whether it is a float
+   fn read_num(&mut self) -> (&str, bool) {
       let pos: usize = self.position;
+      let mut is_float: bool = false;
       while self.is_digit(self.ch) {
          self.read_char()
       }
 
-      self.input[pos..self.position].into()
+      // Only a '.' followed by a digit makes a fraction, so "1.foo" stays an integer
+      let next: char = self.peek_char();
+      if self.ch == '.' && self.is_digit(next) {
+         is_float = true;
+         self.read_char();
+         while self.is_digit(self.ch) {
+            self.read_char()
+         }
+      }
+
+      if self.ch == 'e' || self.ch == 'E' {
+         let next: char = self.peek_char();
+         let after_sign: char = self.peek_char_at(1);
+         let exponent_digits: usize = if self.is_digit(next) {
+            1
+         } else if (next == '+' || next == '-') && self.is_digit(after_sign) {
+            2
+         } else {
+            0
+         };
+
+         if exponent_digits > 0 {
+            is_float = true;
+            for _ in 0..exponent_digits {
+               self.read_char();
+            }
+            while self.is_digit(self.ch) {
+               self.read_char()
+            }
+         }
+      }
+
+      (&self.input[pos..self.position], is_float)
    }
 
+   // Identifiers follow the Unicode XID rules (like Rust's), plus '_' as a starting char
    fn is_letter(&mut self, ch: char) -> bool {
-      return 'a' <= ch && ch <= 'z' || 'A' <= ch && ch <= 'Z' || ch == '_'
+      return unicode_ident::is_xid_start(ch) || ch == '_'
+   }
+
+   fn is_ident_continue(&mut self, ch: char) -> bool {
+      return unicode_ident::is_xid_continue(ch)
    }
 
    fn is_digit(&mut self, ch: char) -> bool {
@@ -73,34 +134,159 @@ impl Lexer {
             '\t' => self.read_char(),
             '\n' => self.read_char(),
             '\r' => self.read_char(),
+            '/' if self.peek_char() == '/' => self.eat_line_comment(),
+            '/' if self.peek_char() == '*' => self.eat_block_comment(),
             _ => break,
          }
       }
    }
 
-   fn read_string(&mut self) -> String {
-      let pos: usize = self.position + 1;
+   // Skips a `// ...` comment up to, but not including, the end of the line
+   fn eat_line_comment(&mut self) {
+      while self.ch != '\n' && self.ch != '\0' {
+         self.read_char()
+      }
+   }
+
+   // Skips a `/* ... */` comment. Block comments nest, so every `/*` inside needs its own `*/`
+   fn eat_block_comment(&mut self) {
+      let (start, line, column) = (self.position, self.line, self.column);
+      self.read_char();
+      self.read_char();
+
+      let mut depth: usize = 1;
+      while depth > 0 {
+         match self.ch {
+            '\0' => {
+               // Point at the opening `/*` rather than the whole rest of the file
+               self.errors.push(ParseError::UnterminatedComment { span: Span::new(start, start + 2, line, column) });
+               return
+            },
+            '*' if self.peek_char() == '/' => {
+               self.read_char();
+               self.read_char();
+               depth -= 1;
+            },
+            '/' if self.peek_char() == '*' => {
+               self.read_char();
+               self.read_char();
+               depth += 1;
+            },
+            _ => self.read_char(),
+         }
+      }
+   }
+
+   // Reads a string literal, or the next part of an interpolated one, and returns its decoded value, escape 
+   //    sequences already replaced. The lexer starts on the opening '"' (or the '}' closing an interpolation) 
+   //    and stops on the closing '"', or on the '{' of a `${` in which case the returned bool is true
+   fn read_string(&mut self) -> (String, bool) {
+      let (start, line, column) = (self.position, self.line, self.column);
+      let mut value: String = String::new();
+      // Start of the run of plain chars that hasn't been copied into value yet
+      let mut pos: usize = self.read_position;
       loop {
          self.read_char();
          if self.ch == '"' || self.ch == '\0' {
             break
          }
+         if self.ch == '$' && self.peek_char() == '{' {
+            value.push_str(&self.input[pos..self.position]);
+            self.read_char();
+            return (value, true)
+         }
+         if self.ch == '\\' {
+            value.push_str(&self.input[pos..self.position]);
+            self.read_escape(&mut value);
+            pos = self.read_position;
+         }
       }
 
-      self.input[pos..self.position].into()
+      if self.ch == '\0' {
+         self.errors.push(ParseError::UnterminatedString { span: self.span_from(start, line, column) });
+      }
+
+      value.push_str(&self.input[pos..self.position.min(self.input.len())]);
+      (value, false)
+   }
+
+   // Decodes the escape sequence starting at the current '\' and leaves the lexer on its last char
+   fn read_escape(&mut self, value: &mut String) {
+      let (start, line, column) = (self.position, self.line, self.column);
+      // A '\' right before the end of the input is part of an unterminated string, read_string reports that
+      if self.peek_char() == '\0' {
+         return
+      }
+      self.read_char();
+
+      let decoded: Result<char, String> = match self.ch {
+         'n' => Ok('\n'),
+         't' => Ok('\t'),
+         'r' => Ok('\r'),
+         '0' => Ok('\0'),
+         '\\' => Ok('\\'),
+         '"' => Ok('"'),
+         '$' => Ok('$'),
+         'u' => self.read_unicode_escape(),
+         other => Err(format!("unknown escape \\{}", other)),
+      };
+
+      match decoded {
+         Ok(ch) => value.push(ch),
+         Err(reason) => {
+            // Keep the raw text so the rest of the string still makes sense
+            value.push_str(&self.input[start..self.read_position]);
+            let span: Span = Span::new(start, self.read_position, line, column);
+            self.errors.push(ParseError::InvalidEscape { span, reason });
+         }
+      }
+   }
+
+   // Reads the `{1F600}` part of a `\u{1F600}` escape, the lexer is on the 'u'
+   fn read_unicode_escape(&mut self) -> Result<char, String> {
+      if self.peek_char() != '{' {
+         return Err(String::from("expected '{' after \\u"))
+      }
+      self.read_char();
+
+      let mut digits: String = String::new();
+      while self.peek_char().is_ascii_hexdigit() {
+         self.read_char();
+         digits.push(self.ch);
+      }
+      if self.peek_char() != '}' {
+         return Err(String::from("expected '}' to close \\u{...}"))
+      }
+      self.read_char();
+
+      if digits.is_empty() || digits.len() > 6 {
+         return Err(format!("\\u{{{}}} must have between 1 and 6 hex digits", digits))
+      }
+      return match char::from_u32(u32::from_str_radix(&digits, 16).unwrap()) {
+         Some(ch) => Ok(ch),
+         None => Err(format!("\\u{{{}}} is not a valid unicode scalar value", digits)),
+      }
+   }
+
+   fn span_from(&self, start: usize, line: usize, column: usize) -> Span {
+      Span::new(start, self.position.min(self.input.len()), line, column)
    }
 
    pub fn next_token(&mut self) -> Result<Token> {
 
       self.eat_whitespace();
+      let (start, line, column) = (self.position, self.line, self.column);
 
-      let tok: Token = match self.ch {
+      let mut tok: Token = match self.ch {
          '=' => {
             if self.peek_char() == '=' {
                let ch: char = self.ch;
                self.read_char();
                let literal: String = ch.to_string() + &self.ch.to_string();
                Token::new(TokenType::EQ, literal.as_str())
+            } else if self.peek_char() == '>' {
+               self.read_char();
+               Token::new(TokenType::FATARROW, "=>")
             } else {
                Token::new(TokenType::ASSIGN, "=")
             }
@@ -119,16 +305,110 @@ impl Lexer {
          ';' => Token::new(TokenType::SEMICOLON, ";"),
          '(' => Token::new(TokenType::LPAREN, "("),
          ')' => Token::new(TokenType::RPAREN, ")"),
-         '{' => Token::new(TokenType::LBRACE, "{"),
-         '}' => Token::new(TokenType::RBRACE, "}"),
+         '{' => {
+            if let Some(depth) = self.templates.last_mut() {
+               *depth += 1;
+            }
+            Token::new(TokenType::LBRACE, "{")
+         },
+         '}' => {
+            match self.templates.last_mut() {
+               // This '}' closes a `${`, so the string it interrupted carries on
+               Some(0) => {
+                  self.templates.pop();
+                  let (value, interpolates) = self.read_string();
+                  if interpolates {
+                     self.templates.push(0);
+                     Token::new(TokenType::TEMPLATEMIDDLE, value.as_str())
+                  } else {
+                     Token::new(TokenType::TEMPLATEEND, value.as_str())
+                  }
+               },
+               Some(depth) => {
+                  *depth -= 1;
+                  Token::new(TokenType::RBRACE, "}")
+               },
+               None => Token::new(TokenType::RBRACE, "}"),
+            }
+         },
          ',' => Token::new(TokenType::COMMA, ","),
+         '+' if self.peek_char() == '=' => {
+            self.read_char();
+            Token::new(TokenType::PLUSASSIGN, "+=")
+         },
+         '-' if self.peek_char() == '=' => {
+            self.read_char();
+            Token::new(TokenType::MINUSASSIGN, "-=")
+         },
+         '*' if self.peek_char() == '=' => {
+            self.read_char();
+            Token::new(TokenType::ASTERISKASSIGN, "*=")
+         },
+         '/' if self.peek_char() == '=' => {
+            self.read_char();
+            Token::new(TokenType::SLASHASSIGN, "/=")
+         },
          '+' => Token::new(TokenType::PLUS, "+"),
          '-' => Token::new(TokenType::MINUS, "-"),
          '/' => Token::new(TokenType::SLASH, "/"),
-         '<' => Token::new(TokenType::LT, "<"),
-         '>' => Token::new(TokenType::GT, ">"),
+         '%' => Token::new(TokenType::PERCENT, "%"),
+         '<' => {
+            if self.peek_char() == '=' {
+               self.read_char();
+               Token::new(TokenType::LTEQ, "<=")
+            } else {
+               Token::new(TokenType::LT, "<")
+            }
+         },
+         '>' => {
+            if self.peek_char() == '=' {
+               self.read_char();
+               Token::new(TokenType::GTEQ, ">=")
+            } else {
+               Token::new(TokenType::GT, ">")
+            }
+         },
+         '?' if self.peek_char() == '?' => {
+            self.read_char();
+            Token::new(TokenType::NULLISH, "??")
+         },
+         '?' if self.peek_char() == '.' => {
+            self.read_char();
+            Token::new(TokenType::QUESTIONDOT, "?.")
+         },
+         '?' => Token::new(TokenType::QUESTION, "?"),
+         '&' if self.peek_char() == '&' => {
+            self.read_char();
+            Token::new(TokenType::AND, "&&")
+         },
+         '|' if self.peek_char() == '|' => {
+            self.read_char();
+            Token::new(TokenType::OR, "||")
+         },
+         '|' if self.peek_char() == '>' => {
+            self.read_char();
+            Token::new(TokenType::PIPELINE, "|>")
+         },
+         '|' => Token::new(TokenType::PIPE, "|"),
+         '.' if self.peek_char() == '.' && self.peek_char_at(1) == '.' => {
+            self.read_char();
+            self.read_char();
+            Token::new(TokenType::ELLIPSIS, "...")
+         },
+         '.' if self.peek_char() == '.' => {
+            self.read_char();
+            Token::new(TokenType::DOTDOT, "..")
+         },
          '*' => Token::new(TokenType::ASTERISK, "*"),
-         '"' => Token::new(TokenType::STRING, self.read_string().as_str()),
+         '"' => {
+            let (value, interpolates) = self.read_string();
+            if interpolates {
+               self.templates.push(0);
+               Token::new(TokenType::TEMPLATESTART, value.as_str())
+            } else {
+               Token::new(TokenType::STRING, value.as_str())
+            }
+         },
          '[' => Token::new(TokenType::LBRACKET, "["),
          ']' => Token::new(TokenType::RBRACKET, "]"),
          ':' => Token::new(TokenType::COLON, ":"),
@@ -139,18 +419,24 @@ impl Lexer {
             if self.is_letter(self.ch) {
                let identifier: &str = self.read_ident();
                let token_type: &TokenType = lookup(identifier);
-               return Ok(Token::new(token_type.clone(), identifier))
+               let tok: Token = Token::new(token_type.clone(), identifier);
+               return Ok(Token { span: self.span_from(start, line, column), ..tok })
             } else if self.is_digit(self.ch) {
-               let literal: &str = self.read_num();
-               return Ok(Token::new(TokenType::INT, literal))
+               let (literal, is_float) = self.read_num();
+               let tok: Token = Token::new(if is_float { TokenType::FLOAT } else { TokenType::INT }, literal);
+               return Ok(Token { span: self.span_from(start, line, column), ..tok })
             } else {
-               Token::new(TokenType::ILLEGAL, &self.ch.to_string())
+               let tok: Token = Token::new(TokenType::ILLEGAL, &self.ch.to_string());
+               let span: Span = Span::new(start, self.read_position, line, column);
+               self.errors.push(ParseError::IllegalCharacter { token: Token { span, ..tok.clone() } });
+               tok
             }
          }
 
       };
 
       self.read_char();
+      tok.span = self.span_from(start, line, column);
       Ok(tok)
    }
 }
@@ -1,14 +1,41 @@
 use std::collections::HashMap;
 use lazy_static::lazy_static;
 
+// A region of the source code. Offsets are byte offsets into the input, line and column are 1-based 
+//    and point at the first character of the region
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq, Default)]
+pub struct Span {
+   pub start: usize,
+   pub end: usize,
+   pub line: usize,
+   pub column: usize,
+}
+impl Span {
+   pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+      Span { start, end, line, column }
+   }
+
+   // The smallest span covering both self and other
+   pub fn merge(&self, other: Span) -> Span {
+      let first: &Span = if self.start <= other.start { self } else { &other };
+      Span { start: first.start, end: self.end.max(other.end), line: first.line, column: first.column }
+   }
+}
+impl std::fmt::Display for Span {
+   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      write!(f, "{}:{}", self.line, self.column)
+   }
+}
+
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub struct Token {
    pub token_type: TokenType,
-   pub literal: String
+   pub literal: String,
+   pub span: Span,
 }
 impl Token {
    pub fn new(tok_type: TokenType, lit: &str) -> Self {
-      Token { token_type: tok_type, literal: lit.to_string() }
+      Token { token_type: tok_type, literal: lit.to_string(), span: Span::default() }
    }
 }
 
@@ -22,7 +49,12 @@ pub enum TokenType {
    EOF,
    IDENT,
    INT,
+   FLOAT,
    ASSIGN,
+   PLUSASSIGN,
+   MINUSASSIGN,
+   ASTERISKASSIGN,
+   SLASHASSIGN,
    PLUS,
    COMMA,
    SEMICOLON, 
@@ -34,10 +66,23 @@ pub enum TokenType {
    BANG, 
    ASTERISK,
    SLASH,
+   PERCENT,
    LT, 
    GT, 
+   LTEQ,
+   GTEQ,
    EQ,
    NOTEQ,
+   AND,
+   OR,
+   PIPE,
+   PIPELINE,
+   QUESTION,
+   NULLISH,
+   QUESTIONDOT,
+   FATARROW,
+   DOTDOT,
+   ELLIPSIS,
    LBRACKET,
    RBRACKET,
    COLON,
@@ -50,9 +95,19 @@ pub enum TokenType {
    IF,
    ELSE,
    RETURN,
+   MATCH,
+   WHILE,
+   FOR,
+   IN,
+   BREAK,
+   CONTINUE,
 
    // Data Types
    STRING,
+   // An interpolated string "a ${x} b ${y} c" is lexed as TEMPLATESTART("a "), x, TEMPLATEMIDDLE(" b "), y, TEMPLATEEND(" c")
+   TEMPLATESTART,
+   TEMPLATEMIDDLE,
+   TEMPLATEEND,
 }
 
 lazy_static! {
@@ -65,6 +120,12 @@ lazy_static! {
       map.insert("if".into(), TokenType::IF);
       map.insert("else".into(), TokenType::ELSE);
       map.insert("return".into(), TokenType::RETURN);
+      map.insert("match".into(), TokenType::MATCH);
+      map.insert("while".into(), TokenType::WHILE);
+      map.insert("for".into(), TokenType::FOR);
+      map.insert("in".into(), TokenType::IN);
+      map.insert("break".into(), TokenType::BREAK);
+      map.insert("continue".into(), TokenType::CONTINUE);
 
       map
    };
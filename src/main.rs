@@ -19,17 +19,19 @@ mod repl;
 mod helper;
 mod objects;
 mod evaluator;
+mod diagnostics;
 mod tests;
 
 use std::{process, env, fs, path::Path};
 use std::io::{self, Write};
 use color_eyre::{Result, eyre::eyre, owo_colors::OwoColorize};
-use objects::Null;
+use objects::{Null, Error};
 
 use crate::lexer::Lexer;
-use crate::objects::environment::Environment;
+use crate::objects::environment::{Environment, Env};
 use crate::parser::Parser;
 use crate::parser::ast::Program;
+use crate::parser::errors::ParseError;
 
 fn main() -> Result<()> {
    color_eyre::install()?;
@@ -74,23 +76,26 @@ fn handle_file_path(path_str: String) -> Result<()> {
 }
 
 fn eval_mky_file(file_content: String) -> Result<()> {
-   let mut env: Environment = Environment::new();
-   let lexer: Lexer = Lexer::new(file_content);
+   let env: Env = Environment::new();
+   let lexer: Lexer = Lexer::new(file_content.clone());
    let mut parser: Parser = Parser::new(lexer);
    let program: Program = match parser.parse_program() {
       Ok(p) => p,
       Err(e) => return Err(e)
    };
    if !parser.errors().is_empty() {
-      print_parser_errors(&parser.errors());
+      print_parser_errors(&parser.errors(), &file_content);
       process::exit(0)
    }
    
-   match evaluator::eval(Box::new(&program), &mut env) {
+   match evaluator::eval(Box::new(&program), &env) {
       Some(e) => {
          // Maybe introduce a special object that is returned when the evaluation is without error and finished
          if e.as_any().is::<Null>() {
             eprintln!("\n{}", "Process exited successfully.".green().bold())
+         } else if let Some(error) = e.as_any().downcast_ref::<Error>() {
+            eprintln!("\n{}", "Process terminated, runtime error:".red().bold());
+            eprint!("{}", error.render(&file_content))
          } else {
             eprintln!("\n{:?}", e.red().bold())
          }
@@ -101,12 +106,12 @@ fn eval_mky_file(file_content: String) -> Result<()> {
    Ok(())
 }
 
-fn print_parser_errors(errors: &[String]) {
+fn print_parser_errors(errors: &[ParseError], source: &str) {
    print!("{}", "Whoops!, We ran into some monkey business here.\n".bold().bright_red());
    print!("{}", " parser errors:\n".bold().bright_green());
 
-   for msg in errors {
-      print!("\t{}\n", msg)
+   for error in errors {
+      print!("{}\n", error.render(source))
    }
 }
 
@@ -1,34 +1,42 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 use crate::objects::Object;
 
+// Scopes are shared: a closure holds on to the same scope it was created in (not a copy of it), so bindings 
+//    made after the closure was created, like the closure's own name, are visible when it gets called
+pub type Env = Rc<RefCell<Environment>>;
+
 #[derive(Clone, Debug)]
 pub struct Environment {
    pub store: HashMap<String, Box<dyn Object>>,
-   pub outer: Option<Box<Environment>>,
+   pub outer: Option<Env>,
 }
 impl Environment {
-   pub fn new() -> Self {
+   pub fn new() -> Env {
       let e: Environment = Environment {
          store: HashMap::new(),
          outer: None,
       };
-      e
+      Rc::new(RefCell::new(e))
    }
 
-   pub fn new_enclosed_env(outer: Environment) -> Self {
-      let mut e: Environment = Self::new();
-      e.outer = Some(Box::new(outer.clone()));
+   pub fn new_enclosed_env(outer: &Env) -> Env {
+      let e: Env = Self::new();
+      e.borrow_mut().outer = Some(Rc::clone(outer));
       e
    }
 
-   pub fn get(&self, name: &str) -> Option<&Box<dyn Object>> {    
-      let mut found: Option<&Box<dyn Object>> = self.store.get(name);
+   pub fn get(&self, name: &str) -> Option<Box<dyn Object>> {    
       // If we can't find a binding in this current environment, and we have an associated outer environment, 
       //    check for that binding there
-      if found.is_none() && self.outer.is_some() {
-         found = self.outer.as_ref().unwrap().get(name);
+      return match self.store.get(name) {
+         Some(found) => Some(found.clone()),
+         None => match &self.outer {
+            Some(outer) => outer.borrow().get(name),
+            None => None,
+         }
       }
-      found
    }
 
    // Here Option::None if the key didnt exist, 
@@ -36,4 +44,17 @@ impl Environment {
    pub fn set(&mut self, name: &str, val: Box<dyn Object>) -> Option<Box<dyn Object>> {
       self.store.insert(name.to_string(), val)       
    }
+
+   // Updates the nearest scope that already has a binding for name, unlike set which always binds in this scope.
+   //    Returns false if no scope has the name
+   pub fn assign(&mut self, name: &str, val: Box<dyn Object>) -> bool {
+      if self.store.contains_key(name) {
+         self.store.insert(name.to_string(), val);
+         return true
+      }
+      return match &self.outer {
+         Some(outer) => outer.borrow_mut().assign(name, val),
+         None => false,
+      }
+   }
 }
@@ -1,16 +1,21 @@
 pub mod environment;
 
-use std::{any::Any, collections::{hash_map::DefaultHasher, HashMap}, hash::Hasher};
+use std::{any::Any, cell::RefCell, collections::{hash_map::DefaultHasher, HashMap, HashSet}, hash::Hasher, rc::Rc};
 use dyn_clone::DynClone;
-use crate::parser::ast::{Identifier, BlockStatement, Node};
-use self::environment::Environment;
+use crate::parser::ast::{Identifier, BlockStatement, Expression, Node, parameters_string};
+use crate::lexer::token::Span;
+use crate::diagnostics;
+use self::environment::Env;
 
 #[derive(Debug, Clone)]
 pub enum ObjectTypes {
    IntegerObj,
+   FloatObj,
    BooleanObj,
    NullObj,
    ReturnValObj,
+   BreakObj,
+   ContinueObj,
    ErrorObj,
    FunctionObj,
    StringObj,
@@ -22,9 +27,12 @@ impl ObjectTypes {
    pub fn to_string(&self) -> String {
       return match self {
          Self::IntegerObj => "INTEGER",
+         Self::FloatObj => "FLOAT",
          Self::BooleanObj => "BOOLEAN",
          Self::NullObj => "NULL",
          Self::ReturnValObj => "RETURN_VALUE",
+         Self::BreakObj => "BREAK",
+         Self::ContinueObj => "CONTINUE",
          Self::ErrorObj => "ERROR",
          Self::FunctionObj => "FUNCTION",
          Self::StringObj => "STRING",
@@ -116,6 +124,52 @@ impl Hashable for Integer {
 
 
 
+#[derive(Copy, Clone, Debug, PartialEq)]         
+pub struct Float {
+   pub value: f64,
+}
+impl Object for Float {
+   fn r#type(&self) -> ObjectType {
+      ObjectTypes::FloatObj.to_string()
+   }
+
+   // Debug formatting always keeps a fractional part or exponent ("3.0", "1e-9"), so floats never look like integers
+   fn inspect(&self) -> String {
+      format!("{:?}", self.value)
+   }
+
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+
+   // NaN isn't equal to anything, not even itself, so it could never be looked up again
+   fn is_hashable(&self) -> bool {
+      !self.value.is_nan()
+   }
+
+   fn downcast_hashable(&self) -> Option<Box<dyn Hashable>> {
+      if self.is_hashable() {
+         Some(Box::new(*self))
+      } else {
+         None
+      }
+   }
+}
+impl Hashable for Float {
+   // Since 1 == 1.0, a float with no fractional part hashes like the equal integer so either one finds the same key.
+   //    Otherwise the bits are hashed, with -0.0 folded into 0.0
+   fn hash_key(&self) -> HashKey {
+      if self.value.fract() == 0.0 && self.value >= i64::MIN as f64 && self.value < i64::MAX as f64 {
+         return (Integer { value: self.value as i64 }).hash_key()
+      }
+
+      let value: f64 = if self.value == 0.0 { 0.0 } else { self.value };
+      HashKey { r#type: self.r#type(), value: value.to_bits() }
+   }
+}
+
+
+
 #[derive(Copy, Clone, Debug, PartialEq)] 
 pub struct Boolean {
    pub value: bool,
@@ -211,13 +265,101 @@ impl Object for ReturnValue {
 
 
 
+// Signals a "break" unwinding to the loop it belongs to, the same way ReturnValue unwinds to its function
+#[derive(Clone, Copy, Debug)]
+pub struct Break;
+impl Object for Break {
+   fn r#type(&self) -> ObjectType {
+      ObjectTypes::BreakObj.to_string()
+   }
+
+   fn inspect(&self) -> String {
+      String::from("break")
+   }
+
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+
+   fn is_hashable(&self) -> bool {
+      false
+   }
+
+   fn downcast_hashable(&self) -> Option<Box<dyn Hashable>> {
+      None
+   }
+}
+
+
+
+// Signals a "continue" unwinding to the loop it belongs to
+#[derive(Clone, Copy, Debug)]
+pub struct Continue;
+impl Object for Continue {
+   fn r#type(&self) -> ObjectType {
+      ObjectTypes::ContinueObj.to_string()
+   }
+
+   fn inspect(&self) -> String {
+      String::from("continue")
+   }
+
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+
+   fn is_hashable(&self) -> bool {
+      false
+   }
+
+   fn downcast_hashable(&self) -> Option<Box<dyn Hashable>> {
+      None
+   }
+}
+
+
+
+// One Monkey function call that a runtime error unwound through
+#[derive(Clone, Debug, PartialEq)] 
+pub struct Frame {
+   pub name: Option<String>,     // The name the function was bound to with "let", if any
+   pub call_site: Span,
+}
+impl std::fmt::Display for Frame {
+   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      match &self.name {
+         Some(name) => write!(f, "at {} (called at {})", name, self.call_site),
+         None => write!(f, "at <anonymous fn> (called at {})", self.call_site),
+      }
+   }
+}
+
 #[derive(Clone, Debug, PartialEq)] 
 pub struct Error {
    pub message: String,
+   pub span: Option<Span>,       // The innermost expression that failed, filled in by the evaluator
+   pub stack: Vec<Frame>,        // Innermost call first
 }
 impl Error {
    pub fn new(message: String) -> Self {
-      Error { message }
+      Error { message, span: None, stack: vec![] }
+   }
+
+   // Renders the error as a snippet of `source` followed by the stack trace
+   pub fn render(&self, source: &str) -> String {
+      let mut out: String = match self.span {
+         Some(span) => diagnostics::render(source, span, &self.message),
+         None => format!("error: {}\n", self.message),
+      };
+
+      if !self.stack.is_empty() {
+         out.push_str("stack trace:\n");
+         for frame in &self.stack {
+            out.push_str(format!("   {}\n", frame).as_str());
+         }
+      }
+
+      out
    }
 }
 impl Object for Error {
@@ -246,11 +388,15 @@ impl Object for Error {
 
 #[derive(Clone, Debug)] 
 pub struct Function {
+   pub name: Option<String>,     // Set when the function gets bound with "let", used for stack traces
    pub params: Option<Vec<Identifier>>,      // Situation: No Params in function => params becomes Some(empty vec), 
                                              // Situation: Params fuck up/syntax is wrong => params is None
+   pub defaults: HashMap<String, Box<dyn Expression>>,   // Evaluated on every call that doesn't pass the parameter
+   pub rest: Option<Identifier>,
    pub body: Option<BlockStatement>,
-   // This environment is a copy of the overall environment that a function is CALLED from
-   pub env: Environment,
+   pub span: Span,               // Where the function literal was written
+   // The environment the function was created in, shared rather than copied so the function sees later bindings
+   pub env: Env,
 }
 impl Object for Function {
    fn r#type(&self) -> ObjectType {
@@ -259,10 +405,7 @@ impl Object for Function {
 
    fn inspect(&self) -> String {
       let mut out: String = String::new();
-      let mut params: Vec<String> = vec![];
-      for p in self.params.as_ref().unwrap() {
-         params.push(p.string())
-      }
+      let params: Vec<String> = parameters_string(self.params.as_ref().unwrap(), &self.defaults, &self.rest);
 
       out.push_str("fn (");
       out.push_str(params.join(", ").as_str());
@@ -355,9 +498,33 @@ impl Object for BuiltIn {
 
 
 
+thread_local! {
+   // The arrays and hashes whose inspect is running right now, keyed by the address of their shared storage
+   static INSPECTING: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+}
+
+// Since arrays and hashes are shared, one can end up holding itself (let a = [1]; a[0] = a;). Running into a
+//    container that is already being inspected further up shows `cycle` instead of recursing forever
+fn inspect_container(address: usize, cycle: &str, inspect: impl FnOnce() -> String) -> String {
+   if !INSPECTING.with(|inspecting| inspecting.borrow_mut().insert(address)) {
+      return cycle.to_string()
+   }
+   let out: String = inspect();
+   INSPECTING.with(|inspecting| inspecting.borrow_mut().remove(&address));
+
+   out
+}
+
+// Arrays and hashes have reference semantics: clones share the same elements, so "arr[0] = 1" is seen through 
+//    every binding of arr. Builtins like push and insert still build new ones
 #[derive(Clone, Debug)] 
 pub struct Array {
-   pub elements: Vec<Box<dyn Object>>,
+   pub elements: Rc<RefCell<Vec<Box<dyn Object>>>>,
+}
+impl Array {
+   pub fn new(elements: Vec<Box<dyn Object>>) -> Self {
+      Array { elements: Rc::new(RefCell::new(elements)) }
+   }
 }
 impl Object for Array {
    fn r#type(&self) -> ObjectType {
@@ -365,17 +532,19 @@ impl Object for Array {
    }
 
    fn inspect(&self) -> String {
-      let mut out: String = String::new();
-      let mut el: Vec<String> = Vec::new();
+      inspect_container(Rc::as_ptr(&self.elements) as usize, "[...]", || {
+         let mut out: String = String::new();
+         let mut el: Vec<String> = Vec::new();
 
-      for e in &self.elements {
-         el.push(e.inspect())
-      }
-      out.push_str("[");
-      out.push_str(el.join(", ").as_str());
-      out.push_str("]");
+         for e in self.elements.borrow().iter() {
+            el.push(e.inspect())
+         }
+         out.push_str("[");
+         out.push_str(el.join(", ").as_str());
+         out.push_str("]");
 
-      out
+         out
+      })
    }
 
    fn as_any(&self) -> &dyn Any {
@@ -401,7 +570,12 @@ pub struct HashPair {
 
 #[derive(Clone, Debug)] 
 pub struct Hash {
-   pub pairs: HashMap<HashKey, HashPair>
+   pub pairs: Rc<RefCell<HashMap<HashKey, HashPair>>>,
+}
+impl Hash {
+   pub fn new(pairs: HashMap<HashKey, HashPair>) -> Self {
+      Hash { pairs: Rc::new(RefCell::new(pairs)) }
+   }
 }
 impl Object for Hash {
    fn r#type(&self) -> ObjectType {
@@ -409,17 +583,19 @@ impl Object for Hash {
    }
 
    fn inspect(&self) -> String {
-      let mut out: String = String::new();
-      let mut pairs: Vec<String> = Vec::new();
-
-      for (_, pair) in &self.pairs {
-         pairs.push(format!("{}: {}", pair.key.inspect(), pair.value.inspect()))
-      }
-      out.push_str("{");
-      out.push_str(pairs.join(", ").as_str());
-      out.push_str("}");
-
-      out
+      inspect_container(Rc::as_ptr(&self.pairs) as usize, "{...}", || {
+         let mut out: String = String::new();
+         let mut pairs: Vec<String> = Vec::new();
+
+         for (_, pair) in self.pairs.borrow().iter() {
+            pairs.push(format!("{}: {}", pair.key.inspect(), pair.value.inspect()))
+         }
+         out.push_str("{");
+         out.push_str(pairs.join(", ").as_str());
+         out.push_str("}");
+
+         out
+      })
    }
 
    fn as_any(&self) -> &dyn Any {
@@ -1,10 +1,11 @@
 use dyn_clone::DynClone;
-use crate::lexer::token::Token;
+use crate::lexer::token::{Token, TokenType, Span};
 use std::{any::Any, collections::{HashMap, hash_map::DefaultHasher}, hash::Hasher};
 
 pub trait Node: DynClone {
    fn token_literal(&self) -> &str;
    fn string(&self) -> String;
+   fn span(&self) -> Span;
    fn node_as_any(&self) -> &dyn Any;
 }
 dyn_clone::clone_trait_object!(Node);
@@ -73,6 +74,13 @@ impl Node for Program {
       out
    }
 
+   fn span(&self) -> Span {
+      match (self.statements.first(), self.statements.last()) {
+         (Some(first), Some(last)) => first.span().merge(last.span()),
+         _ => Span::default(),
+      }
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -94,6 +102,10 @@ impl Node for Identifier {
       self.value.clone()
    }
 
+   fn span(&self) -> Span {
+      self.token.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -113,6 +125,7 @@ impl Expression for Identifier {
 #[derive(Debug, Clone)]
 pub struct LetStatement {
    pub token: Token,   // this should always be TokenType::LET
+   pub span: Span,
    pub name: Identifier,   // LetStatement.name.token_literal() should return the binding value: let foo = 5;   => "foo"
    pub value: Option<Box<dyn Expression>>,
 }
@@ -135,6 +148,10 @@ impl Node for LetStatement {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -151,9 +168,47 @@ impl Statement for LetStatement {
 
 
 
+// let [a, b, ..rest] = arr; or let {"name": n} = person;
+#[derive(Debug, Clone)]
+pub struct DestructuringLetStatement {
+   pub token: Token,
+   pub span: Span,
+   pub pattern: Pattern,   // Always a Pattern::Array or a Pattern::Hash
+   pub value: Box<dyn Expression>,
+}
+impl Node for DestructuringLetStatement {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      format!("{} {} = {};", self.token_literal(), self.pattern.string(), self.value.string())
+   }
+
+   fn span(&self) -> Span {
+      self.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Statement for DestructuringLetStatement {
+   fn statement_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
 #[derive(Debug, Clone)]
 pub struct ReturnStatement {
    pub token: Token,
+   pub span: Span,
    pub return_value: Option<Box<dyn Expression>>,
 }
 impl Node for ReturnStatement {
@@ -174,6 +229,10 @@ impl Node for ReturnStatement {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -190,9 +249,152 @@ impl Statement for ReturnStatement {
 
 
 
+#[derive(Debug, Clone)]
+pub struct WhileStatement {
+   pub token: Token,
+   pub span: Span,
+   pub condition: Box<dyn Expression>,
+   pub body: BlockStatement,
+}
+impl Node for WhileStatement {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      format!("while {} {}", self.condition.string(), self.body.string())
+   }
+
+   fn span(&self) -> Span {
+      self.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Statement for WhileStatement {
+   fn statement_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
+// "for (x in coll)" has one variable, "for (k, v in coll)" has two. What they are bound to depends on the collection:
+//    arrays and strings give (index, element) and hashes give (key, value), a lone variable gets the element or the key
+#[derive(Debug, Clone)]
+pub struct ForStatement {
+   pub token: Token,
+   pub span: Span,
+   pub variables: Vec<Identifier>,
+   pub iterable: Box<dyn Expression>,
+   pub body: BlockStatement,
+}
+impl Node for ForStatement {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      let variables: Vec<String> = self.variables.iter().map(|v| v.string()).collect();
+      format!("for ({} in {}) {}", variables.join(", "), self.iterable.string(), self.body.string())
+   }
+
+   fn span(&self) -> Span {
+      self.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Statement for ForStatement {
+   fn statement_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakStatement {
+   pub token: Token,
+}
+impl Node for BreakStatement {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      format!("{};", self.token_literal())
+   }
+
+   fn span(&self) -> Span {
+      self.token.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Statement for BreakStatement {
+   fn statement_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContinueStatement {
+   pub token: Token,
+}
+impl Node for ContinueStatement {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      format!("{};", self.token_literal())
+   }
+
+   fn span(&self) -> Span {
+      self.token.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Statement for ContinueStatement {
+   fn statement_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
 #[derive(Debug, Clone)]
 pub struct ExpressionStatement {
    pub token: Token,
+   pub span: Span,
    pub expression: Option<Box<dyn Expression>>,
 }
 impl Node for ExpressionStatement {
@@ -208,6 +410,10 @@ impl Node for ExpressionStatement {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -238,6 +444,10 @@ impl Node for IntegerLiteral {
       self.token.literal.clone()
    }
 
+   fn span(&self) -> Span {
+      self.token.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -254,9 +464,44 @@ impl Expression for IntegerLiteral {
 
 
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatLiteral {
+   pub token: Token,
+   pub value: f64,
+}
+impl Node for FloatLiteral {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      self.token.literal.clone()
+   }
+
+   fn span(&self) -> Span {
+      self.token.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Expression for FloatLiteral {
+   fn expression_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
 #[derive(Debug, Clone)]
 pub struct PrefixExpression {
    pub token: Token,
+   pub span: Span,
    pub operator: String,
    pub right: Option<Box<dyn Expression>>,
 }
@@ -273,6 +518,10 @@ impl Node for PrefixExpression {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -292,6 +541,7 @@ impl Expression for PrefixExpression {
 #[derive(Debug, Clone)]
 pub struct InfixExpression {
    pub token: Token, 
+   pub span: Span,
    pub left: Option<Box<dyn Expression>>,
    pub operator: String,
    pub right: Option<Box<dyn Expression>>,
@@ -311,6 +561,10 @@ impl Node for InfixExpression {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -327,6 +581,44 @@ impl Expression for InfixExpression {
 
 
 
+// "x = 5" or a compound assignment like "x += 5". The target is an Identifier or an IndexExpression like "arr[0]"
+#[derive(Debug, Clone)]
+pub struct AssignExpression {
+   pub token: Token,                   // The ASSIGN token "=", or the compound operator like "+="
+   pub span: Span,
+   pub target: Box<dyn Expression>,
+   pub operator: String,
+   pub value: Box<dyn Expression>,
+}
+impl Node for AssignExpression {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      format!("({} {} {})", self.target.string(), self.operator, self.value.string())
+   }
+
+   fn span(&self) -> Span {
+      self.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Expression for AssignExpression {
+   fn expression_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Boolean {
    pub token: Token,
@@ -341,6 +633,10 @@ impl Node for Boolean {
       self.token.literal.clone()
    }
 
+   fn span(&self) -> Span {
+      self.token.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -360,6 +656,7 @@ impl Expression for Boolean {
 #[derive(Debug, Clone)]
 pub struct IfExpression {
    pub token: Token,
+   pub span: Span,
    pub condition: Option<Box<dyn Expression>>,
    pub consequence: Option<BlockStatement>,
    pub alternative: Option<BlockStatement>,
@@ -374,12 +671,16 @@ impl Node for IfExpression {
 
       out.push_str(format!("if {} {}", self.condition.as_ref().unwrap().string(), self.consequence.as_ref().unwrap().string() ).as_str());
       if self.alternative.is_some() {
-         out.push_str(format!("else {}", self.alternative.as_ref().unwrap().string()).as_str());
+         out.push_str(format!(" else {}", self.alternative.as_ref().unwrap().string()).as_str());
       }
 
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -399,6 +700,7 @@ impl Expression for IfExpression {
 #[derive(Debug, Clone)]
 pub struct BlockStatement {
    pub token: Token,
+   pub span: Span,
    pub statements: Vec<Box<dyn Statement>>,
 }
 impl Node for BlockStatement {
@@ -416,6 +718,10 @@ impl Node for BlockStatement {
       out
    } 
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -435,8 +741,11 @@ impl Statement for BlockStatement {
 #[derive(Debug, Clone)]
 pub struct FunctionLiteral {
    pub token: Token,
+   pub span: Span,
    pub params: Option<Vec<Identifier>>,      // Situation: No Params in function => params becomes Some(empty vec), 
                                              // Situation: Params fuck up/syntax is wrong => params is None
+   pub defaults: HashMap<String, Box<dyn Expression>>,   // Parameter name => default value, for "fn(x, y = 10)"
+   pub rest: Option<Identifier>,             // The "...others" parameter, always comes last
    pub body: Option<BlockStatement>,
 }
 impl Node for FunctionLiteral {
@@ -446,10 +755,11 @@ impl Node for FunctionLiteral {
 
    fn string(&self) -> String {
       let mut out: String = String::new();
-      let mut params_str: Vec<String> = vec![];
+      let params_str: Vec<String> = parameters_string(self.params.as_ref().unwrap(), &self.defaults, &self.rest);
 
-      for p in self.params.as_ref().unwrap() {
-         params_str.push(p.string())
+      // Lambdas are shown the way they were written: |x, y| (x + y)
+      if self.token.token_type == TokenType::PIPE || self.token.token_type == TokenType::OR {
+         return format!("|{}| {}", params_str.join(", "), self.body.as_ref().unwrap().string());
       }
 
       out.push_str(format!("{}(", self.token_literal()).as_str());
@@ -459,6 +769,10 @@ impl Node for FunctionLiteral {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -475,9 +789,29 @@ impl Expression for FunctionLiteral {
 
 
 
+// Each parameter as written in the function literal: "x", "y = 10" or "...others"
+pub fn parameters_string(params: &Vec<Identifier>, defaults: &HashMap<String, Box<dyn Expression>>, rest: &Option<Identifier>) -> Vec<String> {
+   let mut params_str: Vec<String> = vec![];
+
+   for p in params {
+      match defaults.get(&p.value) {
+         Some(default) => params_str.push(format!("{} = {}", p.string(), default.string())),
+         None => params_str.push(p.string()),
+      }
+   }
+   if let Some(rest) = rest {
+      params_str.push(format!("...{}", rest.string()));
+   }
+
+   params_str
+}
+
+
+
 #[derive(Debug, Clone)]
 pub struct CallExpression {
    pub token: Token,
+   pub span: Span,
    pub function: Option<Box<dyn Expression>>,   // Identifier or FunctionLiteral
    pub arguments: Option<Vec<Box<dyn Expression>>>, 
 }
@@ -500,6 +834,10 @@ impl Node for CallExpression {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -516,6 +854,43 @@ impl Expression for CallExpression {
 
 
 
+// "y: 2" inside the arguments of a call, the only place it can show up
+#[derive(Debug, Clone)]
+pub struct NamedArgument {
+   pub token: Token,    // The name's IDENT token
+   pub span: Span,
+   pub name: Identifier,
+   pub value: Box<dyn Expression>,
+}
+impl Node for NamedArgument {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      format!("{}: {}", self.name.string(), self.value.string())
+   }
+
+   fn span(&self) -> Span {
+      self.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Expression for NamedArgument {
+   fn expression_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StringLiteral {
    pub token: Token,
@@ -530,6 +905,10 @@ impl Node for StringLiteral {
       self.token.literal.clone()
    }
 
+   fn span(&self) -> Span {
+      self.token.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -546,9 +925,56 @@ impl Expression for StringLiteral {
 
 
 
+// "a ${x} b" has strings ["a ", " b"] and expressions [x], there is always one more string than expressions
+#[derive(Debug, Clone)]
+pub struct InterpolatedString {
+   pub token: Token,                            // The TEMPLATESTART token
+   pub span: Span,
+   pub strings: Vec<String>,
+   pub expressions: Vec<Box<dyn Expression>>,
+}
+impl Node for InterpolatedString {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      let mut out: String = String::new();
+
+      for (i, string) in self.strings.iter().enumerate() {
+         out.push_str(string.as_str());
+         if let Some(expr) = self.expressions.get(i) {
+            out.push_str(format!("${{{}}}", expr.string()).as_str());
+         }
+      }
+
+      out
+   }
+
+   fn span(&self) -> Span {
+      self.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Expression for InterpolatedString {
+   fn expression_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
 #[derive(Debug, Clone)]
 pub struct ArrayLiteral {
    pub token: Token,                              // The LBRACKET token "["
+   pub span: Span,
    pub elements: Vec<Box<dyn Expression>>,        // Arrays can host multiple types 
 }
 impl Node for ArrayLiteral {
@@ -570,6 +996,10 @@ impl Node for ArrayLiteral {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -589,8 +1019,10 @@ impl Expression for ArrayLiteral {
 #[derive(Debug, Clone)]
 pub struct IndexExpression {
    pub token: Token,                   // The LBRACKET token "["
+   pub span: Span,
    pub left: Box<dyn Expression>,
    pub index: Box<dyn Expression>,
+   pub optional: bool,                 // Written as left?.[index], evaluates to null instead of indexing a null left side
 }
 impl Node for IndexExpression {
    fn token_literal(&self) -> &str {
@@ -602,6 +1034,9 @@ impl Node for IndexExpression {
 
       out.push_str("(");
       out.push_str(self.left.string().as_str());
+      if self.optional {
+         out.push_str("?.");
+      }
       out.push_str("[");
       out.push_str(self.index.string().as_str());
       out.push_str("])");
@@ -609,6 +1044,10 @@ impl Node for IndexExpression {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -625,9 +1064,91 @@ impl Expression for IndexExpression {
 
 
 
+// left[start:end] where either bound can be left out, like arr[1:], arr[:-1] or s[:]
+#[derive(Debug, Clone)]
+pub struct SliceExpression {
+   pub token: Token,                   // The LBRACKET token "["
+   pub span: Span,
+   pub left: Box<dyn Expression>,
+   pub start: Option<Box<dyn Expression>>,
+   pub end: Option<Box<dyn Expression>>,
+   pub optional: bool,                 // Written as left?.[start:end]
+}
+impl Node for SliceExpression {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      let start: String = self.start.as_ref().map(|s| s.string()).unwrap_or_default();
+      let end: String = self.end.as_ref().map(|e| e.string()).unwrap_or_default();
+      let optional: &str = if self.optional { "?." } else { "" };
+
+      format!("({}{}[{}:{}])", self.left.string(), optional, start, end)
+   }
+
+   fn span(&self) -> Span {
+      self.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Expression for SliceExpression {
+   fn expression_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
+// cond ? consequence : alternative, the inline form of an IfExpression
+#[derive(Debug, Clone)]
+pub struct ConditionalExpression {
+   pub token: Token,                   // The QUESTION token "?"
+   pub span: Span,
+   pub condition: Box<dyn Expression>,
+   pub consequence: Box<dyn Expression>,
+   pub alternative: Box<dyn Expression>,
+}
+impl Node for ConditionalExpression {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      format!("({} ? {} : {})", self.condition.string(), self.consequence.string(), self.alternative.string())
+   }
+
+   fn span(&self) -> Span {
+      self.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Expression for ConditionalExpression {
+   fn expression_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
+
+
+
 #[derive(Debug, Clone)]
 pub struct HashLiteral {
    pub token: Token,
+   pub span: Span,
    pub pairs: HashMap<Box<dyn Expression>, Box<dyn Expression>>,
 }
 impl Node for HashLiteral {
@@ -649,6 +1170,10 @@ impl Node for HashLiteral {
       out
    }
 
+   fn span(&self) -> Span {
+      self.span
+   }
+
    fn node_as_any(&self) -> &dyn Any {
       self
    }
@@ -662,3 +1187,123 @@ impl Expression for HashLiteral {
       self
    }
 }
+
+
+
+// The left hand side of a match arm or of a destructuring let. Patterns aren't expressions, they only show up
+//    inside of a MatchExpression or a DestructuringLetStatement
+#[derive(Debug, Clone)]
+pub enum Pattern {
+   Wildcard(Token),                                // "_", matches anything without binding it
+   Literal(Box<dyn Expression>),                   // 1, -2.5, "a" or true, matches an equal value
+   Binding(Identifier),                            // x, matches anything and binds it to x
+   Array {                                         // [first, second, ..rest]
+      token: Token,
+      span: Span,
+      elements: Vec<Pattern>,
+      rest: Option<Box<Pattern>>,                  // A Binding or a Wildcard for "..rest" or "..", None if there's no rest
+   },
+   Hash {                                          // {"key": pattern}, other keys of the hash are ignored
+      token: Token,
+      span: Span,
+      pairs: Vec<(Box<dyn Expression>, Pattern)>,
+   },
+   Or(Vec<Pattern>),                               // "a" | "b", matches if any of the alternatives does
+}
+impl Pattern {
+   pub fn string(&self) -> String {
+      return match self {
+         Self::Wildcard(_) => String::from("_"),
+         Self::Literal(literal) => literal.string(),
+         Self::Binding(identifier) => identifier.string(),
+         Self::Array { elements, rest, .. } => {
+            let mut el: Vec<String> = elements.iter().map(|e| e.string()).collect();
+            if let Some(rest) = rest {
+               el.push(match rest.as_ref() {
+                  Self::Binding(identifier) => format!("..{}", identifier.string()),
+                  _ => String::from(".."),
+               });
+            }
+            format!("[{}]", el.join(", "))
+         },
+         Self::Hash { pairs, .. } => {
+            let pairs: Vec<String> = pairs.iter().map(|(k, v)| format!("{}: {}", k.string(), v.string())).collect();
+            format!("{{{}}}", pairs.join(", "))
+         },
+         Self::Or(alternatives) => {
+            let alternatives: Vec<String> = alternatives.iter().map(|a| a.string()).collect();
+            alternatives.join(" | ")
+         },
+      }
+   }
+
+   pub fn span(&self) -> Span {
+      return match self {
+         Self::Wildcard(token) => token.span,
+         Self::Literal(literal) => literal.span(),
+         Self::Binding(identifier) => identifier.span(),
+         Self::Array { span, .. } => *span,
+         Self::Hash { span, .. } => *span,
+         Self::Or(alternatives) => alternatives.first().unwrap().span().merge(alternatives.last().unwrap().span()),
+      }
+   }
+
+   // Whether the pattern matches every value, so no arm after it can ever be reached
+   pub fn is_irrefutable(&self) -> bool {
+      return match self {
+         Self::Wildcard(_) | Self::Binding(_) => true,
+         Self::Or(alternatives) => alternatives.iter().any(|a| a.is_irrefutable()),
+         _ => false,
+      }
+   }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+   pub pattern: Pattern,
+   pub guard: Option<Box<dyn Expression>>,
+   pub body: BlockStatement,                       // An arm like "1 => x" gets a block holding just x
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchExpression {
+   pub token: Token,                               // The MATCH token
+   pub span: Span,
+   pub subject: Box<dyn Expression>,
+   pub arms: Vec<MatchArm>,
+}
+impl Node for MatchExpression {
+   fn token_literal(&self) -> &str {
+      self.token.literal.as_str()
+   }
+
+   fn string(&self) -> String {
+      let mut arms: Vec<String> = Vec::new();
+
+      for arm in &self.arms {
+         match &arm.guard {
+            Some(guard) => arms.push(format!("{} if {} => {}", arm.pattern.string(), guard.string(), arm.body.string())),
+            None => arms.push(format!("{} => {}", arm.pattern.string(), arm.body.string())),
+         }
+      }
+
+      format!("match ({}) {{ {} }}", self.subject.string(), arms.join(", "))
+   }
+
+   fn span(&self) -> Span {
+      self.span
+   }
+
+   fn node_as_any(&self) -> &dyn Any {
+      self
+   }
+}
+impl Expression for MatchExpression {
+   fn expression_node(&self) {}
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+   fn as_node(&self) -> &dyn Node {
+      self
+   }
+}
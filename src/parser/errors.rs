@@ -0,0 +1,63 @@
+use crate::lexer::token::{Token, TokenType, Span};
+use crate::diagnostics;
+
+// Every error the lexer or the parser can report about a piece of source code
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+   ExpectedToken { expected: TokenType, found: Token },
+   NoPrefixParseFn { found: Token },
+   BadIntegerLiteral { token: Token, reason: String },
+   BadFloatLiteral { token: Token, reason: String },
+   UnterminatedString { span: Span },
+   UnterminatedComment { span: Span },
+   InvalidEscape { span: Span, reason: String },
+   IllegalCharacter { token: Token },
+   OutsideLoop { token: Token },
+   InvalidAssignmentTarget { target: String, span: Span },
+   InvalidPattern { found: Token },
+   UnreachableMatchArm { span: Span },
+   PositionalAfterNamedArgument { span: Span },
+}
+impl ParseError {
+   pub fn span(&self) -> Span {
+      return match self {
+         Self::ExpectedToken { found, .. } => found.span,
+         Self::NoPrefixParseFn { found } => found.span,
+         Self::BadIntegerLiteral { token, .. } => token.span,
+         Self::BadFloatLiteral { token, .. } => token.span,
+         Self::UnterminatedString { span } => *span,
+         Self::UnterminatedComment { span } => *span,
+         Self::InvalidEscape { span, .. } => *span,
+         Self::IllegalCharacter { token } => token.span,
+         Self::OutsideLoop { token } => token.span,
+         Self::InvalidAssignmentTarget { span, .. } => *span,
+         Self::InvalidPattern { found } => found.span,
+         Self::UnreachableMatchArm { span } => *span,
+         Self::PositionalAfterNamedArgument { span } => *span,
+      }
+   }
+
+   // Renders the error as a snippet of `source` with carets under the offending code
+   pub fn render(&self, source: &str) -> String {
+      diagnostics::render(source, self.span(), &self.to_string())
+   }
+}
+impl std::fmt::Display for ParseError {
+   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      return match self {
+         Self::ExpectedToken { expected, found } => write!(f, "Expected next token to be {:?}, got {:?} instead.", expected, found.token_type),
+         Self::NoPrefixParseFn { found } => write!(f, "No prefix parse function for {:?} found.", found.token_type),
+         Self::BadIntegerLiteral { token, reason } => write!(f, "Could not parse {} as i64. Error: {}", token.literal, reason),
+         Self::BadFloatLiteral { token, reason } => write!(f, "Could not parse {} as f64. Error: {}", token.literal, reason),
+         Self::UnterminatedString { .. } => write!(f, "Unterminated string literal."),
+         Self::UnterminatedComment { .. } => write!(f, "Unterminated block comment."),
+         Self::InvalidEscape { reason, .. } => write!(f, "Invalid escape sequence in string literal: {}.", reason),
+         Self::IllegalCharacter { token } => write!(f, "Illegal character {:?}.", token.literal),
+         Self::OutsideLoop { token } => write!(f, "'{}' outside of a loop.", token.literal),
+         Self::InvalidAssignmentTarget { target, .. } => write!(f, "Cannot assign to {}.", target),
+         Self::InvalidPattern { found } => write!(f, "Expected a pattern, got {:?} instead.", found.token_type),
+         Self::UnreachableMatchArm { .. } => write!(f, "Unreachable match arm, an earlier arm already matches every value."),
+         Self::PositionalAfterNamedArgument { .. } => write!(f, "Positional argument after a named argument."),
+      }
+   }
+}
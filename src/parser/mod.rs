@@ -1,27 +1,37 @@
 #![allow(unused)]
 pub mod ast;
+pub mod errors;
 
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 
 use crate::parser::ast::{Program, Identifier, LetStatement, Statement, ReturnStatement, PrefixExpression, InfixExpression, Boolean, IfExpression, BlockStatement, FunctionLiteral, CallExpression, IndexExpression};
 use crate::lexer::Lexer;
-use crate::lexer::token::{Token, TokenType};
-use crate::parser::ast::{Expression, ExpressionStatement, IntegerLiteral, StringLiteral};
+use crate::lexer::token::{Token, TokenType, Span};
+use crate::parser::ast::{Expression, ExpressionStatement, IntegerLiteral, FloatLiteral, StringLiteral, InterpolatedString};
 use color_eyre::Result;
 
-use self::ast::{ArrayLiteral, HashLiteral};
+use self::ast::{ArrayLiteral, HashLiteral, AssignExpression, SliceExpression, ConditionalExpression, NamedArgument, DestructuringLetStatement, MatchExpression, MatchArm, Pattern, WhileStatement, ForStatement, BreakStatement, ContinueStatement};
+use self::errors::ParseError;
 
 type PrefixParseFn = fn(&mut Parser) -> Option<Box<dyn Expression>>;
 type InfixParseFn = fn(&mut Parser, Box<dyn Expression>) -> Option<Box<dyn Expression>>;
+// The plain parameters, the default values by parameter name and the rest parameter of a function
+type FunctionParameters = (Vec<Identifier>, HashMap<String, Box<dyn Expression>>, Option<Identifier>);
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub enum Precedence {
    LOWEST = 1,
+   ASSIGN,          // = or += or -= or *= or /=
+   PIPELINE,        // x |> f
+   TERNARY,         // a ? b : c
+   COALESCE,        // a ?? b
+   OR,              // ||
+   AND,             // &&
    EQUALS,          // ==
-   LESSGREATER,     // < or >
+   LESSGREATER,     // < or > or <= or >=
    SUM,             // +
-   PRODUCT,         // *
+   PRODUCT,         // * or / or %
    PREFIX,          // -X or !X
    CALL,            // myFn(X)   
    INDEX,           // array[idx]
@@ -34,12 +44,26 @@ lazy_static! {
       map.insert(TokenType::NOTEQ, Precedence::EQUALS);
       map.insert(TokenType::LT, Precedence::LESSGREATER);
       map.insert(TokenType::GT, Precedence::LESSGREATER);
+      map.insert(TokenType::LTEQ, Precedence::LESSGREATER);
+      map.insert(TokenType::GTEQ, Precedence::LESSGREATER);
+      map.insert(TokenType::ASSIGN, Precedence::ASSIGN);
+      map.insert(TokenType::PLUSASSIGN, Precedence::ASSIGN);
+      map.insert(TokenType::MINUSASSIGN, Precedence::ASSIGN);
+      map.insert(TokenType::ASTERISKASSIGN, Precedence::ASSIGN);
+      map.insert(TokenType::SLASHASSIGN, Precedence::ASSIGN);
+      map.insert(TokenType::PIPELINE, Precedence::PIPELINE);
+      map.insert(TokenType::QUESTION, Precedence::TERNARY);
+      map.insert(TokenType::NULLISH, Precedence::COALESCE);
+      map.insert(TokenType::AND, Precedence::AND);
+      map.insert(TokenType::OR, Precedence::OR);
       map.insert(TokenType::PLUS, Precedence::SUM);
       map.insert(TokenType::MINUS, Precedence::SUM);
       map.insert(TokenType::SLASH, Precedence::PRODUCT);
       map.insert(TokenType::ASTERISK, Precedence::PRODUCT);
+      map.insert(TokenType::PERCENT, Precedence::PRODUCT);
       map.insert(TokenType::LPAREN, Precedence::CALL);
       map.insert(TokenType::LBRACKET, Precedence::INDEX);
+      map.insert(TokenType::QUESTIONDOT, Precedence::INDEX);
 
       map
    };
@@ -51,7 +75,9 @@ pub struct Parser {
    lexer: Lexer,
    cur_token: Token,
    peek_token: Token,
-   pub errors: Vec<String>,
+   pub errors: Vec<ParseError>,
+   // How many loops enclose the current token, break and continue are only allowed when this is above zero
+   loop_depth: usize,
 
    pub prefix_parse_fns: HashMap<TokenType, PrefixParseFn>,
    pub infix_parse_fns: HashMap<TokenType, InfixParseFn>,
@@ -63,6 +89,7 @@ impl Parser {
          cur_token: Token::new(TokenType::UNKNOWN, ""),
          peek_token: Token::new(TokenType::UNKNOWN, ""),
          errors: Vec::new(),
+         loop_depth: 0,
          prefix_parse_fns: HashMap::new(),
          infix_parse_fns: HashMap::new(),
       };
@@ -73,6 +100,7 @@ impl Parser {
       // Register some functions for parsing
       p.register_prefix(TokenType::IDENT, Parser::parse_identifier);
       p.register_prefix(TokenType::INT, Parser::parse_integer_literal);
+      p.register_prefix(TokenType::FLOAT, Parser::parse_float_literal);
       p.register_prefix(TokenType::BANG, Parser::parse_prefix_expression);
       p.register_prefix(TokenType::MINUS, Parser::parse_prefix_expression);
       p.register_prefix(TokenType::TRUE, Parser::parse_boolean);
@@ -80,20 +108,38 @@ impl Parser {
       p.register_prefix(TokenType::LPAREN, Parser::parse_grouped_expr);
       p.register_prefix(TokenType::IF, Parser::parse_if_expression);
       p.register_prefix(TokenType::FUNCTION, Parser::parse_function_literal);
+      p.register_prefix(TokenType::PIPE, Parser::parse_lambda_literal);
+      p.register_prefix(TokenType::OR, Parser::parse_lambda_literal);
       p.register_prefix(TokenType::STRING, Parser::parse_string_literal);
+      p.register_prefix(TokenType::TEMPLATESTART, Parser::parse_interpolated_string);
       p.register_prefix(TokenType::LBRACKET, Parser::parse_array_literal);
       p.register_prefix(TokenType::LBRACE, Parser::parse_hash_literal);
+      p.register_prefix(TokenType::MATCH, Parser::parse_match_expression);
 
       p.register_infix(TokenType::PLUS, Parser::parse_infix_expression);
       p.register_infix(TokenType::MINUS, Parser::parse_infix_expression);
       p.register_infix(TokenType::SLASH, Parser::parse_infix_expression);
       p.register_infix(TokenType::ASTERISK, Parser::parse_infix_expression);
+      p.register_infix(TokenType::PERCENT, Parser::parse_infix_expression);
       p.register_infix(TokenType::EQ, Parser::parse_infix_expression);
       p.register_infix(TokenType::NOTEQ, Parser::parse_infix_expression);
       p.register_infix(TokenType::LT, Parser::parse_infix_expression);
       p.register_infix(TokenType::GT, Parser::parse_infix_expression);
+      p.register_infix(TokenType::LTEQ, Parser::parse_infix_expression);
+      p.register_infix(TokenType::GTEQ, Parser::parse_infix_expression);
+      p.register_infix(TokenType::AND, Parser::parse_infix_expression);
+      p.register_infix(TokenType::OR, Parser::parse_infix_expression);
+      p.register_infix(TokenType::NULLISH, Parser::parse_infix_expression);
+      p.register_infix(TokenType::QUESTION, Parser::parse_conditional_expression);
+      p.register_infix(TokenType::ASSIGN, Parser::parse_assign_expression);
+      p.register_infix(TokenType::PLUSASSIGN, Parser::parse_assign_expression);
+      p.register_infix(TokenType::MINUSASSIGN, Parser::parse_assign_expression);
+      p.register_infix(TokenType::ASTERISKASSIGN, Parser::parse_assign_expression);
+      p.register_infix(TokenType::SLASHASSIGN, Parser::parse_assign_expression);
+      p.register_infix(TokenType::PIPELINE, Parser::parse_pipeline_expression);
       p.register_infix(TokenType::LPAREN, Parser::parse_call_expression);
       p.register_infix(TokenType::LBRACKET, Parser::parse_index_expression);
+      p.register_infix(TokenType::QUESTIONDOT, Parser::parse_optional_index_expression);
 
       p
    }
@@ -104,6 +150,7 @@ impl Parser {
          Ok(tok) => tok,
          Err(e) => return Err(e)
       };
+      self.errors.append(&mut self.lexer.errors);
       Ok(())
    }
 
@@ -115,7 +162,7 @@ impl Parser {
       self.infix_parse_fns.insert(token_type, func);  
    }
 
-   pub fn errors(&self) -> &Vec<String> {
+   pub fn errors(&self) -> &Vec<ParseError> {
       &self.errors
    } 
 
@@ -133,9 +180,17 @@ impl Parser {
       };
 
       while self.cur_token.token_type != TokenType::EOF {
+         // Only a statement that failed to parse needs recovery. Errors that were already recovered from
+         //    inside of a nested block, or that don't stop parsing at all, leave the parser in a good spot
          match self.parse_statement() {
             Some(statement) => program.statements.push(statement),
-            None => {},
+            None => {
+               self.synchronize();
+               // At the top level a closing brace can't end a block, so also skip a ';' following it
+               if self.cur_token_is(TokenType::RBRACE) && self.peek_token_is(TokenType::SEMICOLON) {
+                  self.next_token();
+               }
+            },
          }
          self.next_token();
       }
@@ -146,6 +201,12 @@ impl Parser {
    fn parse_statement(&mut self) -> Option<Box<dyn Statement>> {
       return match self.cur_token.token_type {
          TokenType::LET => {
+            if self.peek_token_is(TokenType::LBRACKET) || self.peek_token_is(TokenType::LBRACE) {
+               return match self.parse_destructuring_let_statement() {
+                  Some(let_stmt) => Some(Box::new(let_stmt)),
+                  None => None,
+               }
+            }
             match self.parse_let_statement() {
                Some(let_stmt) => Some(Box::new(let_stmt)),
                None => None,
@@ -157,6 +218,19 @@ impl Parser {
                None => None,
             }
          },
+         TokenType::WHILE => {
+            match self.parse_while_statement() {
+               Some(while_stmt) => Some(Box::new(while_stmt)),
+               None => None,
+            }
+         },
+         TokenType::FOR => {
+            match self.parse_for_statement() {
+               Some(for_stmt) => Some(Box::new(for_stmt)),
+               None => None,
+            }
+         },
+         TokenType::BREAK | TokenType::CONTINUE => self.parse_loop_control_statement(),
          _ => {
             match self.parse_expression_statement() {
                Some(expr_stmt) => Some(Box::new(expr_stmt)),
@@ -181,13 +255,35 @@ impl Parser {
 
       self.next_token();
 
-      let value: Option<Box<dyn Expression>> = self.parse_expression(Precedence::LOWEST);
+      let value: Option<Box<dyn Expression>> = Some(self.parse_expression(Precedence::LOWEST)?);
 
       if self.peek_token_is(TokenType::SEMICOLON) {
          self.next_token();
       }
       
-      Some(LetStatement { token: cur_token, name, value })
+      Some(LetStatement { span: self.span_from(cur_token.span), token: cur_token, name, value })
+   }
+
+   // let [a, ..rest] = value; or let {"key": a} = value;
+   fn parse_destructuring_let_statement(&mut self) -> Option<DestructuringLetStatement> {
+      let cur_token: Token = self.cur_token.clone();
+
+      self.next_token();
+      let pattern: Pattern = self.parse_single_pattern()?;
+
+      if !self.expect_peek(TokenType::ASSIGN) {
+         return None;
+      }
+
+      self.next_token();
+
+      let value: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
+
+      if self.peek_token_is(TokenType::SEMICOLON) {
+         self.next_token();
+      }
+
+      Some(DestructuringLetStatement { span: self.span_from(cur_token.span), token: cur_token, pattern, value })
    }
 
    fn parse_return_statement(&mut self) -> Option<ReturnStatement> {
@@ -195,36 +291,139 @@ impl Parser {
 
       self.next_token();
 
-      let return_value: Option<Box<dyn Expression>> = self.parse_expression(Precedence::LOWEST);
+      let return_value: Option<Box<dyn Expression>> = Some(self.parse_expression(Precedence::LOWEST)?);
+
+      if self.peek_token_is(TokenType::SEMICOLON) {
+         self.next_token();
+      }
+
+      Some(ReturnStatement { span: self.span_from(cur_token.span), token: cur_token, return_value })
+   }
+
+   fn parse_while_statement(&mut self) -> Option<WhileStatement> {
+      let cur_token: Token = self.cur_token.clone();
+
+      if !self.expect_peek(TokenType::LPAREN) {
+         return None;
+      }
+
+      self.next_token();
+      let condition: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
+
+      if !self.expect_peek(TokenType::RPAREN) {
+         return None;
+      }
+
+      if !self.expect_peek(TokenType::LBRACE) {
+         return None;
+      }
+
+      self.loop_depth += 1;
+      let body: Option<BlockStatement> = self.parse_block_statement();
+      self.loop_depth -= 1;
+      let body: BlockStatement = body?;
+
+      if self.peek_token_is(TokenType::SEMICOLON) {
+         self.next_token();
+      }
+
+      Some(WhileStatement { span: self.span_from(cur_token.span), token: cur_token, condition, body })
+   }
+
+   fn parse_for_statement(&mut self) -> Option<ForStatement> {
+      let cur_token: Token = self.cur_token.clone();
+
+      if !self.expect_peek(TokenType::LPAREN) {
+         return None;
+      }
+
+      let mut variables: Vec<Identifier> = vec![];
+      if !self.expect_peek(TokenType::IDENT) {
+         return None;
+      }
+      variables.push(Identifier { token: self.cur_token.clone(), value: self.cur_token.literal.clone() });
+      if self.peek_token_is(TokenType::COMMA) {
+         self.next_token();
+         if !self.expect_peek(TokenType::IDENT) {
+            return None;
+         }
+         variables.push(Identifier { token: self.cur_token.clone(), value: self.cur_token.literal.clone() });
+      }
+
+      if !self.expect_peek(TokenType::IN) {
+         return None;
+      }
+
+      self.next_token();
+      let iterable: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
+
+      if !self.expect_peek(TokenType::RPAREN) {
+         return None;
+      }
+
+      if !self.expect_peek(TokenType::LBRACE) {
+         return None;
+      }
+
+      self.loop_depth += 1;
+      let body: Option<BlockStatement> = self.parse_block_statement();
+      self.loop_depth -= 1;
+      let body: BlockStatement = body?;
+
+      if self.peek_token_is(TokenType::SEMICOLON) {
+         self.next_token();
+      }
+
+      Some(ForStatement { span: self.span_from(cur_token.span), token: cur_token, variables, iterable, body })
+   }
+
+   // Parses a "break" or "continue"
+   fn parse_loop_control_statement(&mut self) -> Option<Box<dyn Statement>> {
+      let cur_token: Token = self.cur_token.clone();
+
+      if self.loop_depth == 0 {
+         self.errors.push(ParseError::OutsideLoop { token: cur_token });
+         return None;
+      }
 
       if self.peek_token_is(TokenType::SEMICOLON) {
          self.next_token();
       }
 
-      Some(ReturnStatement { token: cur_token, return_value })
+      return match cur_token.token_type {
+         TokenType::BREAK => Some(Box::new(BreakStatement { token: cur_token })),
+         _ => Some(Box::new(ContinueStatement { token: cur_token })),
+      }
    }
 
    fn parse_expression_statement(&mut self) -> Option<ExpressionStatement> {
-      let expr_stmt: ExpressionStatement = ExpressionStatement {
+      let mut expr_stmt: ExpressionStatement = ExpressionStatement {
          token: self.cur_token.clone(),
+         span: self.cur_token.span,
          expression: match self.parse_expression(Precedence::LOWEST) {
             Some(expr) => Some(expr),
-            None => None,
+            None => return None,
          },
       };
       
       if self.peek_token_is(TokenType::SEMICOLON) {
          self.next_token();
       }
+      expr_stmt.span = self.span_from(expr_stmt.span);
 
       Some(expr_stmt)
    }
 
    fn parse_expression(&mut self, precedence: Precedence) -> Option<Box<dyn Expression>> {
+      // The lexer already reported this one
+      if self.cur_token_is(TokenType::ILLEGAL) {
+         return None
+      }
+
       let prefix_fn: &fn(&mut Parser) -> Option<Box<dyn Expression>> = match self.prefix_parse_fns.get(&self.cur_token.token_type) {
          Some(prefix_fn) => prefix_fn,
          None => {
-            self.no_prefix_parse_error(self.cur_token.token_type);
+            self.no_prefix_parse_error();
             return None
          },
       };
@@ -244,7 +443,7 @@ impl Parser {
          
          let ifx: fn(&mut Parser, Box<dyn Expression>) -> Option<Box<dyn Expression>> = *infix_fn;
          self.next_token();
-         left_exp = ifx(self, left_exp).unwrap(); 
+         left_exp = ifx(self, left_exp)?;
       }
 
       Some(left_exp)
@@ -260,8 +459,7 @@ impl Parser {
       let val: i64 = match self.cur_token.literal.parse::<i64>() {
          Ok(num) => num,
          Err(e) => {
-            let msg: String = format!("Could not parse {} as i64. Error: {}", self.cur_token.literal, e);
-            self.errors.push(msg);
+            self.errors.push(ParseError::BadIntegerLiteral { token: self.cur_token.clone(), reason: e.to_string() });
             return None;
          },
       };
@@ -269,28 +467,93 @@ impl Parser {
       Some(Box::new(IntegerLiteral { token: cur_token, value: val }))
    }
 
+   fn parse_float_literal(&mut self) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
+
+      let val: f64 = match self.cur_token.literal.parse::<f64>() {
+         Ok(num) if num.is_finite() => num,
+         Ok(_) => {
+            self.errors.push(ParseError::BadFloatLiteral { token: self.cur_token.clone(), reason: "number too large to fit in target type".to_string() });
+            return None;
+         },
+         Err(e) => {
+            self.errors.push(ParseError::BadFloatLiteral { token: self.cur_token.clone(), reason: e.to_string() });
+            return None;
+         },
+      };
+
+      Some(Box::new(FloatLiteral { token: cur_token, value: val }))
+   }
+
    fn parse_prefix_expression(&mut self) -> Option<Box<dyn Expression>> {
       let cur_token: Token = self.cur_token.clone();
       let operator: String = self.cur_token.literal.clone();
       
       self.next_token();
+      let right: Option<Box<dyn Expression>> = Some(self.parse_expression(Precedence::PREFIX)?);
 
-      Some(Box::new(PrefixExpression { token: cur_token, operator, right: self.parse_expression(Precedence::PREFIX) }))
+      Some(Box::new(PrefixExpression { span: self.span_from(cur_token.span), token: cur_token, operator, right }))
    }
 
    fn parse_infix_expression(&mut self, left: Box<dyn Expression>) -> Option<Box<dyn Expression>> {
       let mut expr: InfixExpression = InfixExpression { 
-         token: self.cur_token.clone(), left: Some(left), operator: self.cur_token.literal.clone(), right: None 
+         token: self.cur_token.clone(), span: left.span(), left: Some(left), operator: self.cur_token.literal.clone(), right: None 
       };
 
       let precedence: Precedence = self.cur_precedence();
       self.next_token();
 
-      expr.right = self.parse_expression(precedence);
+      expr.right = Some(self.parse_expression(precedence)?);
+      expr.span = self.span_from(expr.span);
        
       Some(Box::new(expr))
    }
 
+   // Assignment is right associative, "a = b = 1" is "a = (b = 1)"
+   fn parse_assign_expression(&mut self, target: Box<dyn Expression>) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
+
+      let is_index: bool = target.as_any().downcast_ref::<IndexExpression>().is_some_and(|index| !index.optional);
+      if !target.as_any().is::<Identifier>() && !is_index {
+         self.errors.push(ParseError::InvalidAssignmentTarget { target: target.string(), span: target.span() });
+         return None;
+      }
+
+      self.next_token();
+      let value: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
+
+      Some(Box::new(AssignExpression {
+         span: self.span_from(target.span()),
+         operator: cur_token.literal.clone(),
+         token: cur_token,
+         target,
+         value,
+      }))
+   }
+
+   // There's no node for a pipeline, it gets rewritten into a call right away: x |> f(a) => f(x, a) and x |> f => f(x)
+   fn parse_pipeline_expression(&mut self, left: Box<dyn Expression>) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
+      let precedence: Precedence = self.cur_precedence();
+
+      self.next_token();
+      let right: Box<dyn Expression> = self.parse_expression(precedence)?;
+      let span: Span = self.span_from(left.span());
+
+      // Only a call written right after |> takes `left` as its first argument. A parenthesized one like (f(1))
+      //    is called first and its result gets `left`, the parentheses leave no node behind but end after the call
+      let parenthesized: bool = right.span().end != self.cur_token.span.end;
+
+      return match right.as_any().downcast_ref::<CallExpression>().filter(|_| !parenthesized) {
+         Some(call) => {
+            let mut arguments: Vec<Box<dyn Expression>> = call.arguments.clone().unwrap_or_default();
+            arguments.insert(0, left);
+            Some(Box::new(CallExpression { token: call.token.clone(), span, function: call.function.clone(), arguments: Some(arguments) }))
+         },
+         None => Some(Box::new(CallExpression { token: cur_token, span, function: Some(right), arguments: Some(vec![left]) })),
+      }
+   }
+
    fn parse_boolean(&mut self) -> Option<Box<dyn Expression>> {
       Some(Box::new(Boolean {
          token: self.cur_token.clone(),
@@ -301,7 +564,7 @@ impl Parser {
    fn parse_grouped_expr(&mut self) -> Option<Box<dyn Expression>> {
       self.next_token();
 
-      let expr: Option<Box<dyn Expression>> = self.parse_expression(Precedence::LOWEST);
+      let expr: Option<Box<dyn Expression>> = Some(self.parse_expression(Precedence::LOWEST)?);
 
       if !self.expect_peek(TokenType::RPAREN) {
          return None;
@@ -318,7 +581,7 @@ impl Parser {
       }
 
       self.next_token();
-      let condition: Option<Box<dyn Expression>> = self.parse_expression(Precedence::LOWEST);
+      let condition: Option<Box<dyn Expression>> = Some(self.parse_expression(Precedence::LOWEST)?);
 
       if !self.expect_peek(TokenType::RPAREN) {
          return None;
@@ -328,21 +591,31 @@ impl Parser {
          return None;
       }
 
-      let consequence: Option<BlockStatement> = self.parse_block_statement();
+      let consequence: Option<BlockStatement> = Some(self.parse_block_statement()?);
 
       // Check for "else" block here
       let mut alternative: Option<BlockStatement> = None;
 
       if self.peek_token_is(TokenType::ELSE) {
          self.next_token();
-         if !self.expect_peek(TokenType::LBRACE) {
-            return None;
+         if self.peek_token_is(TokenType::IF) {
+            // "else if" is stored as an else block holding just the nested if
+            self.next_token();
+            let if_token: Token = self.cur_token.clone();
+            let nested_if: Box<dyn Expression> = self.parse_if_expression()?;
+            let statement: ExpressionStatement = ExpressionStatement { token: if_token.clone(), span: nested_if.span(), expression: Some(nested_if) };
+            alternative = Some(BlockStatement { token: if_token, span: statement.span, statements: vec![Box::new(statement)] });
+         } else {
+            if !self.expect_peek(TokenType::LBRACE) {
+               return None;
+            }
+            alternative = Some(self.parse_block_statement()?);
          }
-         alternative = self.parse_block_statement();
       }
 
 
       Some(Box::new(IfExpression {
+         span: self.span_from(cur_token.span),
          token: cur_token,
          condition,
          consequence,
@@ -350,6 +623,204 @@ impl Parser {
       }))
    }
 
+   fn parse_match_expression(&mut self) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
+
+      if !self.expect_peek(TokenType::LPAREN) {
+         return None;
+      }
+
+      self.next_token();
+      let subject: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
+
+      if !self.expect_peek(TokenType::RPAREN) {
+         return None;
+      }
+      if !self.expect_peek(TokenType::LBRACE) {
+         return None;
+      }
+
+      let mut arms: Vec<MatchArm> = vec![];
+      // Set once an arm without a guard matches every value, any arm after that one is dead code
+      let mut has_catch_all: bool = false;
+      while !self.peek_token_is(TokenType::RBRACE) {
+         self.next_token();
+         let arm: MatchArm = self.parse_match_arm()?;
+
+         if has_catch_all {
+            self.errors.push(ParseError::UnreachableMatchArm { span: arm.pattern.span() });
+         }
+         if arm.pattern.is_irrefutable() && arm.guard.is_none() {
+            has_catch_all = true;
+         }
+         // A `{` after `=>` always starts a block, so an expression body never begins with one
+         let arm_has_block_body: bool = arm.body.token.token_type == TokenType::LBRACE;
+         arms.push(arm);
+
+         // Arms are separated by commas, which are optional after a block body and after the last arm
+         if self.peek_token_is(TokenType::COMMA) {
+            self.next_token();
+         } else if !self.peek_token_is(TokenType::RBRACE) && !arm_has_block_body && !self.expect_peek(TokenType::COMMA) {
+            return None;
+         }
+      }
+      self.next_token();
+
+      Some(Box::new(MatchExpression {
+         span: self.span_from(cur_token.span),
+         token: cur_token,
+         subject,
+         arms,
+      }))
+   }
+
+   // pattern [if guard] => body, where body is a block or a single expression
+   fn parse_match_arm(&mut self) -> Option<MatchArm> {
+      let pattern: Pattern = self.parse_pattern()?;
+
+      let mut guard: Option<Box<dyn Expression>> = None;
+      if self.peek_token_is(TokenType::IF) {
+         self.next_token();
+         self.next_token();
+         guard = match self.parse_expression(Precedence::LOWEST) {
+            Some(guard) => Some(guard),
+            None => return None
+         };
+      }
+
+      if !self.expect_peek(TokenType::FATARROW) {
+         return None;
+      }
+
+      self.next_token();
+      let body: BlockStatement = if self.cur_token_is(TokenType::LBRACE) {
+         self.parse_block_statement()?
+      } else {
+         let cur_token: Token = self.cur_token.clone();
+         let expression: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
+         let statement: ExpressionStatement = ExpressionStatement { token: cur_token.clone(), span: expression.span(), expression: Some(expression) };
+         BlockStatement { token: cur_token, span: statement.span, statements: vec![Box::new(statement)] }
+      };
+
+      Some(MatchArm { pattern, guard, body })
+   }
+
+   fn parse_pattern(&mut self) -> Option<Pattern> {
+      let first: Pattern = self.parse_single_pattern()?;
+      if !self.peek_token_is(TokenType::PIPE) {
+         return Some(first);
+      }
+
+      let mut alternatives: Vec<Pattern> = vec![first];
+      while self.peek_token_is(TokenType::PIPE) {
+         self.next_token();
+         self.next_token();
+         match self.parse_single_pattern() {
+            Some(pattern) => alternatives.push(pattern),
+            None => return None
+         }
+      }
+
+      Some(Pattern::Or(alternatives))
+   }
+
+   fn parse_single_pattern(&mut self) -> Option<Pattern> {
+      return match self.cur_token.token_type {
+         TokenType::IDENT => {
+            if self.cur_token.literal == "_" {
+               Some(Pattern::Wildcard(self.cur_token.clone()))
+            } else {
+               Some(Pattern::Binding(Identifier { token: self.cur_token.clone(), value: self.cur_token.literal.clone() }))
+            }
+         },
+         TokenType::LBRACKET => self.parse_array_pattern(),
+         TokenType::LBRACE => self.parse_hash_pattern(),
+         _ => self.parse_literal_pattern().map(Pattern::Literal),
+      }
+   }
+
+   // A literal value inside a pattern: an integer or float (optionally negated), a string or a boolean
+   fn parse_literal_pattern(&mut self) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
+      let is_literal_start: bool = match cur_token.token_type {
+         TokenType::INT | TokenType::FLOAT | TokenType::STRING | TokenType::TRUE | TokenType::FALSE => true,
+         TokenType::MINUS => self.peek_token_is(TokenType::INT) || self.peek_token_is(TokenType::FLOAT),
+         _ => false,
+      };
+      if !is_literal_start {
+         // The lexer already reported this one
+         if !self.cur_token_is(TokenType::ILLEGAL) {
+            self.errors.push(ParseError::InvalidPattern { found: cur_token });
+         }
+         return None;
+      }
+
+      // PREFIX precedence stops before any operator, the literal is all there is to parse
+      self.parse_expression(Precedence::PREFIX)
+   }
+
+   // [first, second, ..rest] or [first, ..] or [first, second]
+   fn parse_array_pattern(&mut self) -> Option<Pattern> {
+      let cur_token: Token = self.cur_token.clone();
+      let mut elements: Vec<Pattern> = vec![];
+      let mut rest: Option<Box<Pattern>> = None;
+
+      while !self.peek_token_is(TokenType::RBRACKET) {
+         self.next_token();
+         if self.cur_token_is(TokenType::DOTDOT) {
+            // The rest has to come last
+            rest = Some(Box::new(if self.peek_token_is(TokenType::IDENT) && self.peek_token.literal != "_" {
+               self.next_token();
+               Pattern::Binding(Identifier { token: self.cur_token.clone(), value: self.cur_token.literal.clone() })
+            } else {
+               if self.peek_token_is(TokenType::IDENT) {
+                  self.next_token();
+               }
+               Pattern::Wildcard(self.cur_token.clone())
+            }));
+            break;
+         }
+
+         match self.parse_pattern() {
+            Some(pattern) => elements.push(pattern),
+            None => return None
+         }
+         if !self.peek_token_is(TokenType::RBRACKET) && !self.expect_peek(TokenType::COMMA) {
+            return None;
+         }
+      }
+      if !self.expect_peek(TokenType::RBRACKET) {
+         return None;
+      }
+
+      Some(Pattern::Array { span: self.span_from(cur_token.span), token: cur_token, elements, rest })
+   }
+
+   // {"key": pattern, 1: pattern}
+   fn parse_hash_pattern(&mut self) -> Option<Pattern> {
+      let cur_token: Token = self.cur_token.clone();
+      let mut pairs: Vec<(Box<dyn Expression>, Pattern)> = vec![];
+
+      while !self.peek_token_is(TokenType::RBRACE) {
+         self.next_token();
+         let key: Box<dyn Expression> = self.parse_literal_pattern()?;
+         if !self.expect_peek(TokenType::COLON) {
+            return None;
+         }
+         self.next_token();
+         match self.parse_pattern() {
+            Some(pattern) => pairs.push((key, pattern)),
+            None => return None
+         }
+         if !self.peek_token_is(TokenType::RBRACE) && !self.expect_peek(TokenType::COMMA) {
+            return None;
+         }
+      }
+      self.next_token();
+
+      Some(Pattern::Hash { span: self.span_from(cur_token.span), token: cur_token, pairs })
+   }
+
    fn parse_block_statement(&mut self) -> Option<BlockStatement> {
       let cur_token: Token = self.cur_token.clone();
       let mut statements: Vec<Box<dyn Statement>> = vec![];
@@ -357,14 +828,25 @@ impl Parser {
       self.next_token();
 
       while !self.cur_token_is(TokenType::RBRACE) && !self.cur_token_is(TokenType::EOF) {
-         let statement: Option<Box<dyn Statement>> = self.parse_statement();
-         if statement.is_some() {
-            statements.push(statement.unwrap())
+         match self.parse_statement() {
+            Some(statement) => statements.push(statement),
+            None => {
+               self.synchronize();
+               // Recovery stopped on the brace that closes this block
+               if self.cur_token_is(TokenType::RBRACE) {
+                  break;
+               }
+            },
          }
          self.next_token();
       }
 
-      Some(BlockStatement { token: cur_token, statements })
+      if self.cur_token_is(TokenType::EOF) {
+         self.errors.push(ParseError::ExpectedToken { expected: TokenType::RBRACE, found: self.cur_token.clone() });
+         return None;
+      }
+
+      Some(BlockStatement { span: self.span_from(cur_token.span), token: cur_token, statements })
    }
 
    fn parse_function_literal(&mut self) -> Option<Box<dyn Expression>> {
@@ -374,57 +856,134 @@ impl Parser {
          return None;
       }
 
-      let params: Option<Vec<Identifier>> = self.parse_function_parameters();
+      let (params, defaults, rest) = self.parse_function_parameters(TokenType::RPAREN)?;
 
       if !self.expect_peek(TokenType::LBRACE) {
          return None;
       }
 
+      // A loop around the function literal doesn't make break or continue valid inside of its body
+      let loop_depth: usize = self.loop_depth;
+      self.loop_depth = 0;
       let body: Option<BlockStatement> = self.parse_block_statement();
+      self.loop_depth = loop_depth;
+      body.as_ref()?;
 
       Some(Box::new(FunctionLiteral {
+         span: self.span_from(cur_token.span),
          token: cur_token,
-         params,
+         params: Some(params),
+         defaults,
+         rest,
          body,
       }))
    }
 
-   fn parse_function_parameters(&mut self) -> Option<Vec<Identifier>> {
-      let mut identifiers: Vec<Identifier> = vec![];
+   // |x, y| x + y or || 5, shorthand for a FunctionLiteral. The body is a single expression, or a block when it starts with "{"
+   fn parse_lambda_literal(&mut self) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
 
-      if self.peek_token_is(TokenType::RPAREN) {
-         self.next_token();
-         return Some(identifiers)        // If we instantly see a RPAREN, then there are no parameters to the function: Empty vec is returned
-      }
+      // "||" comes out of the lexer as a single OR token, that's a lambda without parameters
+      let (params, defaults, rest) = if self.cur_token_is(TokenType::OR) {
+         (vec![], HashMap::new(), None)
+      } else {
+         self.parse_function_parameters(TokenType::PIPE)?
+      };
 
       self.next_token();
+      let loop_depth: usize = self.loop_depth;
+      self.loop_depth = 0;
+      let body: Option<BlockStatement> = if self.cur_token_is(TokenType::LBRACE) {
+         self.parse_block_statement()
+      } else {
+         let body_token: Token = self.cur_token.clone();
+         match self.parse_expression(Precedence::LOWEST) {
+            Some(expression) => {
+               let statement: ExpressionStatement = ExpressionStatement { token: body_token.clone(), span: expression.span(), expression: Some(expression) };
+               Some(BlockStatement { token: body_token, span: statement.span, statements: vec![Box::new(statement)] })
+            },
+            None => None
+         }
+      };
+      self.loop_depth = loop_depth;
+      body.as_ref()?;
 
-      let ident: Identifier = Identifier { token: self.cur_token.clone(), value: self.cur_token.literal.clone() };
-      identifiers.push(ident);
+      Some(Box::new(FunctionLiteral {
+         span: self.span_from(cur_token.span),
+         token: cur_token,
+         params: Some(params),
+         defaults,
+         rest,
+         body,
+      }))
+   }
 
-      while self.peek_token_is(TokenType::COMMA) {
+   // x, y = 10, ...others up to and including the `end` token
+   fn parse_function_parameters(&mut self, end: TokenType) -> Option<FunctionParameters> {
+      let mut identifiers: Vec<Identifier> = vec![];
+      let mut defaults: HashMap<String, Box<dyn Expression>> = HashMap::new();
+
+      if self.peek_token_is(end) {
          self.next_token();
-         self.next_token();     // We see a comma, so skip past the comma and to the next actual token
-         
+         return Some((identifiers, defaults, None))        // If we instantly see the end token, then there are no parameters to the function: Empty vec is returned
+      }
+
+      loop {
+         // The rest parameter collects whatever is left, so nothing can come after it
+         if self.peek_token_is(TokenType::ELLIPSIS) {
+            self.next_token();
+            if !self.expect_peek(TokenType::IDENT) {
+               return None
+            }
+            let rest: Identifier = Identifier { token: self.cur_token.clone(), value: self.cur_token.literal.clone() };
+            if !self.expect_peek(end) {
+               return None
+            }
+            return Some((identifiers, defaults, Some(rest)))
+         }
+
+         if !self.expect_peek(TokenType::IDENT) {
+            return None
+         }
          let ident: Identifier = Identifier { token: self.cur_token.clone(), value: self.cur_token.literal.clone() };
+
+         if self.peek_token_is(TokenType::ASSIGN) {
+            self.next_token();
+            self.next_token();
+            match self.parse_expression(Precedence::LOWEST) {
+               Some(default) => defaults.insert(ident.value.clone(), default),
+               None => return None
+            };
+         }
          identifiers.push(ident);
+
+         if !self.peek_token_is(TokenType::COMMA) {
+            break;
+         }
+         // We see a comma, so skip past the comma and to the next actual token
+         self.next_token();
       }
 
-      if !self.expect_peek(TokenType::RPAREN) {
+      if !self.expect_peek(end) {
          return None
       }
 
-      Some(identifiers)
+      Some((identifiers, defaults, None))
    }
 
    fn parse_call_expression(&mut self, function: Box<dyn Expression>) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
+      let arguments: Option<Vec<Box<dyn Expression>>> = Some(self.parse_call_arguments()?);
+
       Some(Box::new(CallExpression {
-         token: self.cur_token.clone(),
+         token: cur_token,
+         span: self.span_from(function.span()),
          function: Some(function),
-         arguments: self.parse_expression_list(TokenType::RPAREN),     // arguments: self.parse_call_arguments(), 
+         arguments,
       }))
    }
 
+   // Like parse_expression_list, except an argument can also be named as in "f(x, y: 2)"
    fn parse_call_arguments(&mut self) -> Option<Vec<Box<dyn Expression>>> {
       let mut args: Vec<Box<dyn Expression>> = vec![];
 
@@ -433,13 +992,23 @@ impl Parser {
          return Some(args)
       }
 
-      self.next_token();
-      args.push(self.parse_expression(Precedence::LOWEST).unwrap());
-      
-      while self.peek_token_is(TokenType::COMMA) {
+      let mut seen_named: bool = false;
+      loop {
          self.next_token();
+         let arg: Box<dyn Expression> = self.parse_call_argument()?;
+
+         // Positional arguments fill parameters from the left, so they can't come after a named one
+         if arg.as_any().is::<NamedArgument>() {
+            seen_named = true;
+         } else if seen_named {
+            self.errors.push(ParseError::PositionalAfterNamedArgument { span: arg.span() });
+         }
+         args.push(arg);
+
+         if !self.peek_token_is(TokenType::COMMA) {
+            break;
+         }
          self.next_token();
-         args.push(self.parse_expression(Precedence::LOWEST).unwrap());
       }
 
       if !self.expect_peek(TokenType::RPAREN) {
@@ -449,14 +1018,64 @@ impl Parser {
       Some(args)
    }
 
+   fn parse_call_argument(&mut self) -> Option<Box<dyn Expression>> {
+      if !(self.cur_token_is(TokenType::IDENT) && self.peek_token_is(TokenType::COLON)) {
+         return self.parse_expression(Precedence::LOWEST)
+      }
+
+      let cur_token: Token = self.cur_token.clone();
+      let name: Identifier = Identifier { token: cur_token.clone(), value: cur_token.literal.clone() };
+      self.next_token();
+      self.next_token();
+
+      let value: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
+
+      Some(Box::new(NamedArgument { span: cur_token.span.merge(value.span()), token: cur_token, name, value }))
+   }
+
    fn parse_string_literal(&mut self) -> Option<Box<dyn Expression>> {
       Some(Box::new(StringLiteral { token: self.cur_token.clone(), value: self.cur_token.literal.clone() }))
    }
 
+   fn parse_interpolated_string(&mut self) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
+      let mut strings: Vec<String> = vec![cur_token.literal.clone()];
+      let mut expressions: Vec<Box<dyn Expression>> = Vec::new();
+
+      loop {
+         self.next_token();
+         match self.parse_expression(Precedence::LOWEST) {
+            Some(expr) => expressions.push(expr),
+            None => return None
+         }
+
+         if self.peek_token_is(TokenType::TEMPLATEMIDDLE) {
+            self.next_token();
+            strings.push(self.cur_token.literal.clone());
+         } else if self.expect_peek(TokenType::TEMPLATEEND) {
+            strings.push(self.cur_token.literal.clone());
+            break
+         } else {
+            return None
+         }
+      }
+
+      Some(Box::new(InterpolatedString {
+         span: self.span_from(cur_token.span),
+         token: cur_token,
+         strings,
+         expressions,
+      }))
+   }
+
    fn parse_array_literal(&mut self) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
+      let elements: Vec<Box<dyn Expression>> = self.parse_expression_list(TokenType::RBRACKET)?;
+
       Some(Box::new(ArrayLiteral {
-         token: self.cur_token.clone(),
-         elements: self.parse_expression_list(TokenType::RBRACKET).unwrap()
+         span: self.span_from(cur_token.span),
+         token: cur_token,
+         elements,
       }))
    }
 
@@ -477,8 +1096,10 @@ impl Parser {
       while self.peek_token_is(TokenType::COMMA) {
          self.next_token();
          self.next_token();
-         // If it panics here on unwrap then it just means that the expression is unsupported or syntactically wrong
-         list.push(self.parse_expression(Precedence::LOWEST).unwrap());
+         match self.parse_expression(Precedence::LOWEST) {
+            Some(expr) => list.push(expr),
+            None => return None
+         }
       }
 
       if !self.expect_peek(end) {
@@ -489,15 +1110,70 @@ impl Parser {
    }
 
    fn parse_index_expression(&mut self, left: Box<dyn Expression>) -> Option<Box<dyn Expression>> {
+      self.parse_index(left, false)
+   }
+
+   // left?.[index]
+   fn parse_optional_index_expression(&mut self, left: Box<dyn Expression>) -> Option<Box<dyn Expression>> {
+      if !self.expect_peek(TokenType::LBRACKET) {
+         return None
+      }
+
+      self.parse_index(left, true)
+   }
+
+   // left[index] or the slice left[start:end], where both bounds are optional
+   fn parse_index(&mut self, left: Box<dyn Expression>, optional: bool) -> Option<Box<dyn Expression>> {
       let cur_token: Token = self.cur_token.clone();
       self.next_token();
-      let index: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST).unwrap();
-      
+
+      let mut start: Option<Box<dyn Expression>> = None;
+      if !self.cur_token_is(TokenType::COLON) {
+         start = Some(self.parse_expression(Precedence::LOWEST)?);
+         if !self.peek_token_is(TokenType::COLON) {
+            if !self.expect_peek(TokenType::RBRACKET) {
+               return None
+            }
+            return Some(Box::new(IndexExpression { token: cur_token, span: self.span_from(left.span()), left, index: start.unwrap(), optional }))
+         }
+         self.next_token();
+      }
+
+      // Now on the colon of a slice
+      let mut end: Option<Box<dyn Expression>> = None;
+      if !self.peek_token_is(TokenType::RBRACKET) {
+         self.next_token();
+         end = Some(self.parse_expression(Precedence::LOWEST)?);
+      }
       if !self.expect_peek(TokenType::RBRACKET) {
          return None
       }
 
-      Some(Box::new(IndexExpression { token: cur_token, left, index }))
+      Some(Box::new(SliceExpression { token: cur_token, span: self.span_from(left.span()), left, start, end, optional }))
+   }
+
+   // condition ? consequence : alternative
+   fn parse_conditional_expression(&mut self, condition: Box<dyn Expression>) -> Option<Box<dyn Expression>> {
+      let cur_token: Token = self.cur_token.clone();
+
+      self.next_token();
+      let consequence: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
+
+      if !self.expect_peek(TokenType::COLON) {
+         return None
+      }
+
+      // One level below TERNARY so a ? b : c ? d : e nests to the right
+      self.next_token();
+      let alternative: Box<dyn Expression> = self.parse_expression(Precedence::PIPELINE)?;
+
+      Some(Box::new(ConditionalExpression {
+         span: self.span_from(condition.span()),
+         token: cur_token,
+         condition,
+         consequence,
+         alternative,
+      }))
    }
 
    fn parse_hash_literal(&mut self) -> Option<Box<dyn Expression>> {
@@ -506,13 +1182,13 @@ impl Parser {
       
       while !self.peek_token_is(TokenType::RBRACE) {
          self.next_token();
-         let key: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST).unwrap();
+         let key: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
          if !self.expect_peek(TokenType::COLON) {
             return None
          }
 
          self.next_token();
-         let value: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST).unwrap();
+         let value: Box<dyn Expression> = self.parse_expression(Precedence::LOWEST)?;
          pairs.insert(key, value);
 
          if !self.peek_token_is(TokenType::RBRACE) && !self.expect_peek(TokenType::COMMA) {
@@ -524,7 +1200,7 @@ impl Parser {
          return None
       }
 
-      Some(Box::new(HashLiteral { token: cur_token, pairs}))
+      Some(Box::new(HashLiteral { span: self.span_from(cur_token.span), token: cur_token, pairs}))
    }
 
 
@@ -533,6 +1209,11 @@ impl Parser {
 
    // HELPER FUNCTIONS 
 
+   // Span running from the start of `start` up to the end of the token currently being looked at
+   fn span_from(&self, start: Span) -> Span {
+      start.merge(self.cur_token.span)
+   }
+
    fn cur_token_is(&mut self, token_type: TokenType) -> bool {
       self.cur_token.token_type == token_type
    }
@@ -552,13 +1233,30 @@ impl Parser {
    }
 
    fn peek_error(&mut self, token_type: TokenType) {
-      let msg: String = format!("Expected next token to be {:?}, got {:?} instead.", token_type, self.peek_token.token_type);
-      self.errors.push(msg);
+      // The lexer already reported this one
+      if self.peek_token_is(TokenType::ILLEGAL) {
+         return;
+      }
+      self.errors.push(ParseError::ExpectedToken { expected: token_type, found: self.peek_token.clone() });
    }
 
-   fn no_prefix_parse_error(&mut self, token_type: TokenType) {
-      let msg: String = format!("No prefix parse function for {:?} found.", token_type);
-      self.errors.push(msg);
+   fn no_prefix_parse_error(&mut self) {
+      self.errors.push(ParseError::NoPrefixParseFn { found: self.cur_token.clone() });
+   }
+
+   // Panic mode recovery: skips the rest of a statement that failed to parse, so one mistake doesn't cascade into 
+   //    a pile of bogus errors. Leaves cur_token on the last token of the bad statement, so the next call to 
+   //    next_token lands on the start of the following statement (or on the brace closing the enclosing block)
+   fn synchronize(&mut self) {
+      loop {
+         if self.cur_token_is(TokenType::SEMICOLON) || self.cur_token_is(TokenType::RBRACE) || self.cur_token_is(TokenType::EOF) {
+            return;
+         }
+         match self.peek_token.token_type {
+            TokenType::LET | TokenType::RETURN | TokenType::WHILE | TokenType::FOR | TokenType::EOF => return,
+            _ => self.next_token(),
+         };
+      }
    }
 
    fn peek_precedence(&mut self) -> Precedence {
@@ -2,15 +2,16 @@ use std::io::{BufRead, Write};
 use color_eyre::owo_colors::OwoColorize;
 
 use crate::lexer::Lexer;
-use crate::objects::environment::Environment;
+use crate::objects::environment::{Environment, Env};
 use crate::parser::Parser;
 use crate::parser::ast::Program;
+use crate::parser::errors::ParseError;
 use crate::evaluator;
 
 const PROMPT: &str = ">> ";
 
 pub fn start<R: BufRead, W: Write>(mut reader: R, mut writer: W) {
-   let mut env: Environment = Environment::new();
+   let env: Env = Environment::new();
    loop {
       write!(writer, "{}", PROMPT).expect("Failed to write prompt");
       writer.flush().expect("Failed to flush output");
@@ -32,11 +33,11 @@ pub fn start<R: BufRead, W: Write>(mut reader: R, mut writer: W) {
                Err(e) => panic!("{}", e),
             };
             if !parser.errors().is_empty() {
-               print_parser_errors(&mut writer, &parser.errors());
+               print_parser_errors(&mut writer, &parser.errors(), trimmed_line);
                continue;
             }
             
-            match evaluator::eval(Box::new(&program), &mut env) {
+            match evaluator::eval(Box::new(&program), &env) {
                Some(e) => {
                   write!(writer, "{}\n", e.inspect()).expect("Failed to write Evaluation")
                },
@@ -48,11 +49,11 @@ pub fn start<R: BufRead, W: Write>(mut reader: R, mut writer: W) {
    }
 }
 
-fn print_parser_errors<W: Write>(writer: &mut W, errors: &[String]) {
+fn print_parser_errors<W: Write>(writer: &mut W, errors: &[ParseError], source: &str) {
    write!(writer, "{}", "Whoops!, We ran into some monkey business here.\n".bold().bright_red()).expect("Failed to write MONKEY_FACE");
    write!(writer, "{}", " parser errors:\n".bold().bright_green()).expect("Failed to write parser errors clause");
 
-   for msg in errors {
-      write!(writer, "\t{}\n", msg).expect("Failed to write parser error")
+   for error in errors {
+      write!(writer, "{}\n", error.render(source)).expect("Failed to write parser error")
    }
 }
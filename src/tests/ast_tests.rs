@@ -1,6 +1,6 @@
 #[cfg(test)]
 
-use crate::lexer::token::{Token, TokenType};
+use crate::lexer::token::{Token, TokenType, Span};
 
 #[test]
 fn test_string() {
@@ -11,6 +11,7 @@ fn test_string() {
       statements: vec![
          Box::new(LetStatement {
             token: Token::new(TokenType::LET, "let"),
+            span: Span::default(),
 
             name: Identifier {
                token: Token::new(TokenType::IDENT, "myVar"),
@@ -4,8 +4,9 @@ use std::any::Any;
 use std::collections::HashMap;
 use maplit::hashmap;
 
-use crate::objects::environment::Environment;
-use crate::objects::{Integer, Boolean, Object, Null, Error, ReturnValue, Function, MkyString, Array, Hash, Hashable};
+use crate::objects::environment::{Environment, Env};
+use crate::objects::{Integer, Float, Boolean, Object, Null, Error, ReturnValue, Function, MkyString, Array, Hash, Hashable, Frame};
+use crate::lexer::token::Span;
 use crate::parser::Parser;
 use crate::lexer::Lexer;
 use crate::parser::ast::{Program, Node};
@@ -42,6 +43,23 @@ impl i64Test {
    }
 }
 
+struct FloatTest {
+   input: String,
+   expected: f64,
+}
+impl FloatTest {
+   pub fn new(input: &str, expected: f64) -> Self {
+      FloatTest { input: input.to_string(), expected }
+   }
+
+   pub fn test_me(&self) {
+      match test_eval(self.input.clone()) {
+         Some(eval) => test_float_object(eval, self.expected),
+         None => panic!("test_eval returned None")
+      }
+   }
+}
+
 struct BoolTest {
    input: String,
    expected: bool,
@@ -173,9 +191,9 @@ fn test_eval(input: String) -> Option<Box<dyn Object>> {
       Ok(p) => p,
       Err(e) => panic!("{}", e),
    };
-   let mut env: Environment = Environment::new();
+   let env: Env = Environment::new();
 
-   return eval(Box::new(&program), &mut env)
+   return eval(Box::new(&program), &env)
 }
 
 
@@ -187,6 +205,14 @@ fn test_integer_object(obj: Box<dyn Object>, expected: i64) {
    }
 }
 
+fn test_float_object(obj: Box<dyn Object>, expected: f64) {
+   if let Some(result) = obj.as_any().downcast_ref::<Float>() {
+      assert_eq!(result.value, expected);
+   } else {
+      panic!("obj passed is not a Float object. Got {:?}", obj)
+   }
+}
+
 fn test_boolean_object(obj: Box<dyn Object>, expected: bool) {
    if let Some(result) = obj.as_any().downcast_ref::<Boolean>() {
       assert_eq!(result.value, expected);
@@ -248,6 +274,12 @@ fn test_eval_boolean_expression() {
    BoolTest::new("(1 < 2) == false", false).test_me();
    BoolTest::new("(1 > 2) == true", false).test_me();
    BoolTest::new("(1 > 2) == false", true).test_me();
+   BoolTest::new("1 <= 1", true).test_me();
+   BoolTest::new("2 <= 1", false).test_me();
+   BoolTest::new("1 >= 1", true).test_me();
+   BoolTest::new("1 >= 2", false).test_me();
+   BoolTest::new("1.5 <= 2", true).test_me();
+   BoolTest::new("2.5 >= 3", false).test_me();
 }
 
 #[test]
@@ -377,6 +409,49 @@ fn test_string_literal() {
    }
 }
 
+#[test]
+fn test_unicode_strings_and_identifiers() {
+   match test_eval(String::from("let größe = \"café\"; größe + \" ☕\"")) {
+      Some(eval) => {
+         if let Some(str_obj) = eval.as_any().downcast_ref::<MkyString>() {
+            assert_eq!(str_obj.value, "café ☕")
+         } else {
+            panic!("object returned from test_eval is not a Monkey String")
+         }
+      }
+      None => panic!("test_eval returned None.")
+   }
+   i64Test::new("let 数 = 21; 数 * 2", 42).test_me();
+}
+
+#[test]
+fn test_string_interpolation() {
+   let tests: Vec<(&str, &str)> = vec![
+      (r#"let price = 3; let qty = 4; "total: ${price * qty}""#, "total: 12"),
+      (r#""${[1, 2]} and ${true} and ${1.5}""#, "[1, 2] and true and 1.5"),
+      (r#"let name = "Monkey"; "hello, ${name}!""#, "hello, Monkey!"),
+      (r#""outer ${"inner ${1 + 1}"}""#, "outer inner 2"),
+      (r#""${ {"a": 1}["a"] }""#, "1"),
+      (r#""\${not interpolated}""#, "${not interpolated}"),
+   ];
+
+   for (input, expected) in tests {
+      match test_eval(input.to_string()) {
+         Some(eval) => {
+            if let Some(str_obj) = eval.as_any().downcast_ref::<MkyString>() {
+               assert_eq!(str_obj.value, expected)
+            } else {
+               panic!("object returned from test_eval is not a Monkey String. Got {:?}", eval)
+            }
+         }
+         None => panic!("test_eval returned None.")
+      }
+   }
+
+   ErrorMessageTest::new(r#""a ${1 / 0} b""#, "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new(r#""a ${missing} b""#, "identifier not found: missing").test_me();
+}
+
 #[test]
 fn test_string_concat() {
    let input: String = String::from("\"Hello\" + \" \" + \"World!\"");
@@ -396,6 +471,11 @@ fn test_string_concat() {
 fn test_builtin_functions() {
    BuiltInTest::new("len(\"\")", 0).test_me();
    BuiltInTest::new("len(\"four\")", 4).test_me();
+   BuiltInTest::new("len(\"a\\nb\")", 3).test_me();
+   BuiltInTest::new("len(\"café 😀\")", 6).test_me();
+   BuiltInTest::new("byte_len(\"café 😀\")", 10).test_me();
+   BuiltInTest::new("byte_len(\"\")", 0).test_me();
+   BuiltInTest::new("byte_len([1])", String::from("argument to 'byte_len' must be STRING, got ARRAY")).test_me();
    BuiltInTest::new("len(\"hello world\")", 11).test_me();
    BuiltInTest::new("len(1)", "argument to 'len' not supported, got INTEGER".to_string()).test_me();
    BuiltInTest::new("len(\"one\", \"two\")", "wrong number of arguments. got=2, want=1".to_string()).test_me();
@@ -407,10 +487,10 @@ fn test_array_literals() {
    match test_eval(input) {
       Some(eval) => {
          if let Some(array_obj) = eval.as_any().downcast_ref::<Array>() {
-            assert_eq!(array_obj.elements.len(), 3);
-            test_integer_object(array_obj.elements.get(0).unwrap().clone(), 1);
-            test_integer_object(array_obj.elements.get(1).unwrap().clone(), 4);
-            test_integer_object(array_obj.elements.get(2).unwrap().clone(), 6);
+            assert_eq!(array_obj.elements.borrow().len(), 3);
+            test_integer_object(array_obj.elements.borrow().first().unwrap().clone(), 1);
+            test_integer_object(array_obj.elements.borrow().get(1).unwrap().clone(), 4);
+            test_integer_object(array_obj.elements.borrow().get(2).unwrap().clone(), 6);
          } else {
             panic!("eval is not Array")
          }
@@ -430,7 +510,10 @@ fn test_array_index_expressions() {
    ArrayIndexExpressionsTest::new("let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];", Some(6)).test_me();
    ArrayIndexExpressionsTest::new("let myArray = [1, 2, 3]; let i = myArray[0]; myArray[i]", Some(2)).test_me();
    ArrayIndexExpressionsTest::new("[1, 2, 3][3]", None).test_me();
-   ArrayIndexExpressionsTest::new("[1, 2, 3][-1]", None).test_me();
+   ArrayIndexExpressionsTest::new("[1, 2, 3][-1]", Some(3)).test_me();
+   ArrayIndexExpressionsTest::new("[1, 2, 3][-3]", Some(1)).test_me();
+   ArrayIndexExpressionsTest::new("[1, 2, 3][-4]", None).test_me();
+   ArrayIndexExpressionsTest::new("[][-1]", None).test_me();
 }
 
 #[test]
@@ -458,10 +541,10 @@ fn test_hash_literals() {
                TRUE.hash_key() => 5,
                FALSE.hash_key() => 6,
             };
-            assert_eq!(hash_obj.pairs.len(), expected.len());
+            assert_eq!(hash_obj.pairs.borrow().len(), expected.len());
 
             for (expected_key, expected_value) in &expected {
-               if let Some(pair) = hash_obj.pairs.get(expected_key) {
+               if let Some(pair) = hash_obj.pairs.borrow().get(expected_key) {
                   test_integer_object(pair.value.clone(), expected_value.clone())
                } else {
                   panic!("no pair for given key in Pairs")
@@ -487,3 +570,638 @@ fn test_hash_index_expressions() {
    ArrayIndexExpressionsTest::new("{true: 5}[true]", Some(5)).test_me();
    ArrayIndexExpressionsTest::new("{false: 5}[false]", Some(5)).test_me();
 }
+
+#[test]
+fn test_error_location_and_stack_trace() {
+   let input: String = String::from("let add = fn(a, b) {\n   a + b\n};\nlet twice = fn(x) { add(x, \"1\") };\ntwice(2);");
+   match test_eval(input) {
+      Some(eval) => {
+         if let Some(error_object) = eval.as_any().downcast_ref::<Error>() {
+            assert_eq!(error_object.message, "type mismatch: INTEGER + STRING");
+            assert_eq!(error_object.span, Some(Span::new(24, 29, 2, 4)));
+            assert_eq!(error_object.stack, vec![
+               Frame { name: Some("add".to_string()), call_site: Span::new(53, 64, 4, 21) },
+               Frame { name: Some("twice".to_string()), call_site: Span::new(68, 76, 5, 1) },
+            ]);
+         } else {
+            panic!("No error object returned. Got {:?}", eval)
+         }
+      },
+      None => panic!("test_eval returned None.")
+   }
+
+   // Errors outside of any function have no stack, and anonymous functions are still reported
+   match test_eval(String::from("let x = 1;\nx + true;\nfn() { -true }();")) {
+      Some(eval) => {
+         let error_object: &Error = eval.as_any().downcast_ref::<Error>().unwrap();
+         assert_eq!(error_object.span, Some(Span::new(11, 19, 2, 1)));
+         assert!(error_object.stack.is_empty());
+      },
+      None => panic!("test_eval returned None.")
+   }
+   match test_eval(String::from("fn() { -true }();")) {
+      Some(eval) => {
+         let error_object: &Error = eval.as_any().downcast_ref::<Error>().unwrap();
+         assert_eq!(error_object.span, Some(Span::new(7, 12, 1, 8)));
+         assert_eq!(error_object.stack, vec![Frame { name: None, call_site: Span::new(0, 16, 1, 1) }]);
+      },
+      None => panic!("test_eval returned None.")
+   }
+}
+
+#[test]
+fn test_recursive_functions() {
+   i64Test::new("
+      let fib = fn(n) {
+         if (n < 2) { return n; }
+         fib(n - 1) + fib(n - 2)
+      };
+      fib(15);
+      ", 610).test_me();
+
+   BoolTest::new("
+      let isEven = fn(n) { if (n == 0) { true } else { isOdd(n - 1) } };
+      let isOdd = fn(n) { if (n == 0) { false } else { isEven(n - 1) } };
+      isEven(10);
+      ", true).test_me();
+
+   // Closures see bindings made after they were created, in the scope they were created in
+   i64Test::new("
+      let getLater = fn() { later };
+      let later = 42;
+      getLater();
+      ", 42).test_me();
+
+   i64Test::new("
+      let makeCounter = fn(start) {
+         let count = fn(n) { if (n == 0) { start } else { count(n - 1) + 1 } };
+         count
+      };
+      makeCounter(10)(5);
+      ", 15).test_me();
+}
+
+#[test]
+fn test_function_arity() {
+   ErrorMessageTest::new("let add = fn(x, y) { x + y; }; add(1);", "wrong number of arguments: want=2, got=1 (add defined at 1:11)").test_me();
+   ErrorMessageTest::new("let add = fn(x, y) { x + y; };\nadd(1, 2, 3);", "wrong number of arguments: want=2, got=3 (add defined at 1:11)").test_me();
+   ErrorMessageTest::new("fn() { 1 }(1);", "wrong number of arguments: want=0, got=1 (fn defined at 1:1)").test_me();
+   ErrorMessageTest::new("let f = fn(x) { x }; let g = fn() { f() }; g();", "wrong number of arguments: want=1, got=0 (f defined at 1:9)").test_me();
+
+   // The error points at the bad call
+   match test_eval(String::from("let id = fn(x) { x };\nid();")) {
+      Some(eval) => {
+         let error_object: &Error = eval.as_any().downcast_ref::<Error>().unwrap();
+         assert_eq!(error_object.span, Some(Span::new(22, 26, 2, 1)));
+      },
+      None => panic!("test_eval returned None.")
+   }
+}
+
+#[test]
+fn test_checked_integer_arithmetic() {
+   ErrorMessageTest::new("1 / 0", "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new("let zero = 5 - 5; 10 / zero;", "division by zero: 10 / 0").test_me();
+   ErrorMessageTest::new("9223372036854775807 + 1", "integer overflow: 9223372036854775807 + 1").test_me();
+   ErrorMessageTest::new("-9223372036854775807 - 2", "integer overflow: -9223372036854775807 - 2").test_me();
+   ErrorMessageTest::new("4611686018427387904 * 2", "integer overflow: 4611686018427387904 * 2").test_me();
+   ErrorMessageTest::new("let min = -9223372036854775807 - 1; min / -1", "integer overflow: -9223372036854775808 / -1").test_me();
+   ErrorMessageTest::new("let min = -9223372036854775807 - 1; -min", "integer overflow: -(-9223372036854775808)").test_me();
+
+   i64Test::new("9223372036854775806 + 1", 9223372036854775807).test_me();
+   i64Test::new("-9223372036854775807 - 1", -9223372036854775808).test_me();
+   i64Test::new("7 / -2", -3).test_me();
+}
+
+#[test]
+fn test_modulo() {
+   i64Test::new("7 % 3", 1).test_me();
+   i64Test::new("-7 % 3", -1).test_me();
+   i64Test::new("6 % 3 + 1", 1).test_me();
+   FloatTest::new("7.5 % 2", 1.5).test_me();
+
+   ErrorMessageTest::new("7 % 0", "division by zero: 7 % 0").test_me();
+   ErrorMessageTest::new("7.5 % 0", "division by zero: 7.5 % 0.0").test_me();
+   ErrorMessageTest::new("let min = -9223372036854775807 - 1; min % -1", "integer overflow: -9223372036854775808 % -1").test_me();
+   ErrorMessageTest::new("\"a\" % \"b\"", "unknown operator: STRING % STRING").test_me();
+}
+
+#[test]
+fn test_logical_operators() {
+   BoolTest::new("true && true", true).test_me();
+   BoolTest::new("true && false", false).test_me();
+   BoolTest::new("false || true", true).test_me();
+   BoolTest::new("false || false", false).test_me();
+   BoolTest::new("1 < 2 && 2 < 3", true).test_me();
+   BoolTest::new("1 && 0", true).test_me();
+   BoolTest::new("if (false) { 1 } || \"\"", true).test_me();
+
+   // The right side isn't evaluated when the left side already decides the result
+   BoolTest::new("false && 1 / 0", false).test_me();
+   BoolTest::new("true || undefined_name", true).test_me();
+   BoolTest::new("let calls = fn() { 1 / 0 }; false && calls()", false).test_me();
+   ErrorMessageTest::new("true && 1 / 0", "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new("false || missing", "identifier not found: missing").test_me();
+}
+
+#[test]
+fn test_float_expressions() {
+   FloatTest::new("1.25", 1.25).test_me();
+   FloatTest::new("-2.5", -2.5).test_me();
+   FloatTest::new("1.5 + 1.5", 3.0).test_me();
+   FloatTest::new("3.5 + 1", 4.5).test_me();
+   FloatTest::new("1 - 0.25", 0.75).test_me();
+   FloatTest::new("2 * 1.5", 3.0).test_me();
+   FloatTest::new("1 / 2.0", 0.5).test_me();
+   FloatTest::new("let total = 7; let count = 2; total / (count * 1.0)", 3.5).test_me();
+   FloatTest::new("1e3 + 1", 1001.0).test_me();
+
+   BoolTest::new("1 == 1.0", true).test_me();
+   BoolTest::new("1.5 != 1.5", false).test_me();
+   BoolTest::new("1.5 < 2", true).test_me();
+   BoolTest::new("2 > 2.5", false).test_me();
+
+   ErrorMessageTest::new("1.5 / 0", "division by zero: 1.5 / 0.0").test_me();
+   ErrorMessageTest::new("1e308 * 10.0", "float overflow: 1e308 * 10.0").test_me();
+   ErrorMessageTest::new("1e308 + 1e308", "float overflow: 1e308 + 1e308").test_me();
+   ErrorMessageTest::new("-1e308 - 1e308", "float overflow: -1e308 - 1e308").test_me();
+   ErrorMessageTest::new("1.5 + true", "type mismatch: FLOAT + BOOLEAN").test_me();
+
+   match test_eval(String::from("1.5 * 2")) {
+      Some(eval) => assert_eq!(eval.inspect(), "3.0"),
+      None => panic!("test_eval returned None.")
+   }
+}
+
+#[test]
+fn test_float_hash_keys() {
+   ArrayIndexExpressionsTest::new("{1: 10}[1.0]", Some(10)).test_me();
+   ArrayIndexExpressionsTest::new("{1.0: 10}[1]", Some(10)).test_me();
+   ArrayIndexExpressionsTest::new("{1.5: 10}[1.5]", Some(10)).test_me();
+   ArrayIndexExpressionsTest::new("{1.5: 10}[1]", None).test_me();
+   ArrayIndexExpressionsTest::new("{0.0: 10}[-0.0]", Some(10)).test_me();
+}
+
+#[test]
+fn test_while_loops() {
+   i64Test::new("let i = 0; while (i < 5) { let i = i + 1; } i", 5).test_me();
+   i64Test::new("let i = 0; while (false) { let i = i + 1; } i", 0).test_me();
+
+   // Odd numbers up to 7: the even ones are skipped with continue, and break leaves the loop at 9
+   let input: &str = "
+      let i = 0;
+      let total = 0;
+      while (i < 10) {
+         let i = i + 1;
+         if (i % 2 == 0) { continue; }
+         if (i > 7) { break; }
+         let total = total + i;
+      }
+      total * 100 + i
+   ";
+   i64Test::new(input, 1609).test_me();
+
+   // break only leaves the innermost loop
+   let input: &str = "
+      let outer = 0;
+      let count = 0;
+      while (outer < 3) {
+         let outer = outer + 1;
+         let inner = 0;
+         while (true) {
+            let inner = inner + 1;
+            let count = count + 1;
+            if (inner == 2) { break; }
+         }
+      }
+      count
+   ";
+   i64Test::new(input, 6).test_me();
+
+   // return leaves the loop and the function around it
+   let input: &str = "
+      let find = fn(limit) {
+         let i = 0;
+         while (true) {
+            if (i * i > limit) { return i; }
+            let i = i + 1;
+         }
+      };
+      find(50)
+   ";
+   i64Test::new(input, 8).test_me();
+
+   ErrorMessageTest::new("while (1 / 0) { }", "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new("let i = 0; while (i < 3) { let i = i + 1; if (i == 2) { missing } }", "identifier not found: missing").test_me();
+
+   // A loop evaluates to null, so it can end a block whose value gets used
+   IfElseTest::new("while (false) { }", None).test_me();
+   IfElseTest::new("if (true) { while (false) {} }", None).test_me();
+   IfElseTest::new("let f = fn() { while (false) {} }; let x = f(); x", None).test_me();
+   IfElseTest::new("let i = 0; while (true) { break; }", None).test_me();
+   i64Test::new("let i = 0; while (i < 3) { let i = i + 1; }; i", 3).test_me();
+}
+
+#[test]
+fn test_for_loops() {
+   // Loop variables live in their own scope, so results are carried out of the loop with return
+   let input: &str = "
+      let index_of = fn(arr, target) {
+         for (i, x in arr) {
+            if (x == target) { return i; }
+         }
+         -1
+      };
+      index_of([5, 6, 7], 7) * 10 + index_of([5, 6, 7], 8)
+   ";
+   i64Test::new(input, 19).test_me();
+
+   let input: &str = "
+      let find_value = fn(hash, target) {
+         for (k, v in hash) {
+            if (v == target) { return k; }
+         }
+      };
+      find_value({\"a\": 1, \"b\": 2, \"c\": 3}, 2)
+   ";
+   match test_eval(input.to_string()) {
+      Some(eval) => assert_eq!(eval.inspect(), "b"),
+      None => panic!("test_eval returned None.")
+   }
+
+   let input: &str = "
+      let has_key = fn(hash, target) {
+         for (k in hash) {
+            if (k == target) { return true; }
+         }
+         false
+      };
+      has_key({1: \"one\", 2: \"two\"}, 2)
+   ";
+   BoolTest::new(input, true).test_me();
+
+   let input: &str = "
+      let position_of_char = fn(string, target) {
+         for (i, c in string) {
+            if (c == target) { return i; }
+         }
+      };
+      position_of_char(\"naïve\", \"v\")
+   ";
+   i64Test::new(input, 3).test_me();
+
+   let input: &str = "
+      let first_odd_over = fn(limit) {
+         for (n in range(100)) {
+            if (n % 2 == 0) { continue; }
+            if (n > limit) { return n; }
+         }
+      };
+      first_odd_over(10)
+   ";
+   i64Test::new(input, 11).test_me();
+
+   // break leaves the loop, the code after it still runs
+   i64Test::new("let f = fn() { for (x in [1, 2, 3]) { if (x == 2) { break; } } 42 }; f()", 42).test_me();
+
+   // Each iteration has its own binding, so closures made in different iterations don't share it
+   let input: &str = "
+      let make = fn() {
+         for (x in [1, 2, 3]) {
+            let get = fn() { x };
+            if (x == 2) { return get; }
+         }
+      };
+      make()()
+   ";
+   i64Test::new(input, 2).test_me();
+
+   // The loop variables don't leak out of the loop
+   ErrorMessageTest::new("for (x in [1]) { } x", "identifier not found: x").test_me();
+   ErrorMessageTest::new("for (x in 5) { }", "cannot iterate over INTEGER").test_me();
+   ErrorMessageTest::new("for (x in [1, 2]) { x / 0 }", "division by zero: 1 / 0").test_me();
+   IfElseTest::new("for (x in [1]) { x }", None).test_me();
+   IfElseTest::new("for (x in [1, 2]) { break; }", None).test_me();
+   IfElseTest::new("if (true) { for (x in []) {} }", None).test_me();
+   ErrorMessageTest::new("let f = fn() { for (x in [1]) { } }; f() + 1", "type mismatch: NULL + INTEGER").test_me();
+   i64Test::new("let f = fn(xs) { for (x in xs) { if (x > 1) { return x; } }; 0 }; f([1, 2]) + f([1])", 2).test_me();
+}
+
+#[test]
+fn test_range_builtin() {
+   let tests: Vec<(&str, &str)> = vec![
+      ("range(4)", "[0, 1, 2, 3]"),
+      ("range(2, 5)", "[2, 3, 4]"),
+      ("range(0, 10, 3)", "[0, 3, 6, 9]"),
+      ("range(5, 0, -2)", "[5, 3, 1]"),
+      ("range(3, 1)", "[]"),
+      ("range(-2)", "[]"),
+      ("range(9223372036854775806, 9223372036854775807, 5)", "[9223372036854775806]"),
+   ];
+   for (input, expected) in tests {
+      match test_eval(input.to_string()) {
+         Some(eval) => assert_eq!(eval.inspect(), expected, "\nfor input {}", input),
+         None => panic!("test_eval returned None.")
+      }
+   }
+
+   ErrorMessageTest::new("range()", "wrong number of arguments. got=0, want=1 to 3").test_me();
+   ErrorMessageTest::new("range(1, \"5\")", "arguments to 'range' must be INTEGER, got STRING").test_me();
+   ErrorMessageTest::new("range(1, 5, 0)", "'range' step must not be zero").test_me();
+}
+
+#[test]
+fn test_assignment() {
+   i64Test::new("let x = 1; x = 2; x", 2).test_me();
+   i64Test::new("let x = 1; x = x + 5", 6).test_me();
+   i64Test::new("let a = 1; let b = 2; a = b = 7; a + b", 14).test_me();
+   i64Test::new("let x = 10; x += 5; x -= 3; x *= 2; x /= 4; x", 6).test_me();
+   FloatTest::new("let x = 1; x /= 2.0; x", 0.5).test_me();
+   match test_eval(String::from("let s = \"a\"; s += \"b\"; s")) {
+      Some(eval) => assert_eq!(eval.inspect(), "ab"),
+      None => panic!("test_eval returned None.")
+   }
+
+   // Assignment updates the binding where it lives, not the innermost scope
+   let input: &str = "
+      let make_counter = fn() {
+         let count = 0;
+         fn() { count += 1; count }
+      };
+      let counter = make_counter();
+      counter();
+      counter();
+      counter()
+   ";
+   i64Test::new(input, 3).test_me();
+
+   i64Test::new("let total = 0; for (x in [1, 2, 3, 4]) { total += x; } total", 10).test_me();
+   i64Test::new("let i = 0; let n = 0; while (i < 5) { i += 1; n += i; } n", 15).test_me();
+
+   // A let inside a function still shadows, leaving the outer binding alone
+   i64Test::new("let x = 1; let f = fn() { let x = 2; x = 3; x }; f() * 10 + x", 31).test_me();
+
+   ErrorMessageTest::new("y = 1", "cannot assign to undefined variable: y").test_me();
+   ErrorMessageTest::new("y += 1", "identifier not found: y").test_me();
+   ErrorMessageTest::new("let x = 1; x += \"a\"", "type mismatch: INTEGER + STRING").test_me();
+   ErrorMessageTest::new("let x = 1; x /= 0", "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new("let x = 1; x = missing", "identifier not found: missing").test_me();
+}
+
+#[test]
+fn test_index_assignment() {
+   i64Test::new("let a = [1, 2, 3]; a[1] = 20; a[0] + a[1] + a[2]", 24).test_me();
+   i64Test::new("let a = [1, 2, 3]; a[2] += 10; a[2]", 13).test_me();
+   i64Test::new("let h = {\"a\": 1}; h[\"a\"] = 5; h[\"b\"] = 6; h[\"a\"] * 10 + h[\"b\"]", 56).test_me();
+   i64Test::new("let h = {\"n\": 1}; h[\"n\"] *= 7; h[\"n\"]", 7).test_me();
+   i64Test::new("let grid = [[1, 2], [3, 4]]; grid[1][0] = 30; grid[1][0]", 30).test_me();
+   i64Test::new("let a = [0]; (a[0] = 9) + 1", 10).test_me();
+   i64Test::new("let a = [1, 2, 3]; a[-1] = 9; a[2]", 9).test_me();
+   i64Test::new("let a = [1, 2, 3]; a[-3] += 10; a[0]", 11).test_me();
+
+   // Arrays and hashes are shared, not copied, by let, function calls and closures
+   i64Test::new("let a = [1, 2]; let b = a; b[0] = 100; a[0]", 100).test_me();
+   i64Test::new("let set_first = fn(arr, v) { arr[0] = v }; let a = [1]; set_first(a, 5); a[0]", 5).test_me();
+   let input: &str = "
+      let counts = {\"a\": 0, \"b\": 0, \"c\": 0};
+      for (word in [\"a\", \"b\", \"a\", \"c\", \"a\"]) {
+         counts[word] += 1;
+      }
+      counts[\"a\"] * 100 + counts[\"b\"] * 10 + counts[\"c\"]
+   ";
+   i64Test::new(input, 311).test_me();
+
+   // push and insert still build new arrays and hashes
+   i64Test::new("let a = [1]; let b = push(a, 2); b[0] = 50; len(a) * 100 + a[0]", 101).test_me();
+   i64Test::new("let h = {1: 1}; let g = insert(h, 2, 2); g[1] = 9; h[1]", 1).test_me();
+
+   // A container stored inside itself is printed as [...] or {...} instead of recursing forever
+   let tests: Vec<(&str, &str)> = vec![
+      ("let a = [1]; a[0] = a; a", "[[...]]"),
+      ("let h = {}; h[\"self\"] = h; h", "{self: {...}}"),
+      ("let a = [1, 2]; let h = {\"a\": a}; a[1] = h; a", "[1, {a: [...]}]"),
+      ("let a = [1]; let b = [a, a]; b", "[[1], [1]]"),
+   ];
+   for (input, expected) in tests {
+      match test_eval(input.to_string()) {
+         Some(eval) => assert_eq!(eval.inspect(), expected, "\nfor input {}", input),
+         None => panic!("test_eval returned None.")
+      }
+   }
+
+   ErrorMessageTest::new("let a = [1, 2]; a[2] = 3", "index out of bounds: 2 (length 2)").test_me();
+   ErrorMessageTest::new("let a = [1, 2]; a[-3] = 3", "index out of bounds: -3 (length 2)").test_me();
+   ErrorMessageTest::new("let a = [1, 2]; a[\"x\"] = 3", "array index must be INTEGER, got STRING").test_me();
+   ErrorMessageTest::new("let h = {}; h[[1]] = 3", "unusable as hash key: ARRAY").test_me();
+   ErrorMessageTest::new("let s = \"abc\"; s[0] = \"x\"", "index assignment not supported: STRING").test_me();
+   ErrorMessageTest::new("let a = [1]; a[0] += \"x\"", "type mismatch: INTEGER + STRING").test_me();
+   ErrorMessageTest::new("missing[0] = 1", "identifier not found: missing").test_me();
+}
+
+#[test]
+fn test_else_if_expressions() {
+   let classify: &str = "
+      let classify = fn(n) {
+         if (n < 0) { \"negative\" } else if (n == 0) { \"zero\" } else if (n < 10) { \"small\" } else { \"large\" }
+      };
+   ";
+   for (n, expected) in [("-5", "negative"), ("0", "zero"), ("7", "small"), ("12", "large")] {
+      match test_eval(format!("{} classify({})", classify, n)) {
+         Some(eval) => assert_eq!(eval.inspect(), expected, "\nfor n = {}", n),
+         None => panic!("test_eval returned None.")
+      }
+   }
+
+   IfElseTest::new("if (false) { 1 } else if (false) { 2 }", None).test_me();
+   IfElseTest::new("if (false) { 1 } else if (true) { 2 }", Some(2)).test_me();
+   i64Test::new("let f = fn(x) { if (x == 1) { return 10; } else if (x == 2) { return 20; } 30 }; f(2) + f(3)", 50).test_me();
+   ErrorMessageTest::new("if (false) { 1 } else if (1 / 0) { 2 }", "division by zero: 1 / 0").test_me();
+}
+
+#[test]
+fn test_match_expressions() {
+   let describe: &str = "
+      let describe = fn(x) {
+         match (x) {
+            1 | 2 => \"small\",
+            -3 => \"minus three\",
+            \"a\" | \"b\" => \"letter\",
+            [first, ..rest] if len(rest) > 1 => \"long list starting with ${first}\",
+            [a, b] => a + b,
+            [] => \"empty\",
+            {\"name\": n, \"age\": age} => \"${n} is ${age}\",
+            true => { \"yes\" }
+            _ => \"other\",
+         }
+      };
+   ";
+   let tests: Vec<(&str, &str)> = vec![
+      ("1", "small"),
+      ("2.0", "small"),
+      ("-3", "minus three"),
+      ("\"b\"", "letter"),
+      ("[9, 8, 7]", "long list starting with 9"),
+      ("[1, 2]", "3"),
+      ("[]", "empty"),
+      ("[1]", "other"),
+      ("{\"name\": \"Ann\", \"age\": 3, \"extra\": 1}", "Ann is 3"),
+      ("{\"name\": \"Ann\"}", "other"),
+      ("true", "yes"),
+      ("\"1\"", "other"),
+   ];
+   for (input, expected) in tests {
+      match test_eval(format!("{} describe({})", describe, input)) {
+         Some(eval) => assert_eq!(eval.inspect(), expected, "\nfor input {}", input),
+         None => panic!("test_eval returned None.")
+      }
+   }
+
+   i64Test::new("match ([1, 2, 3]) { [_, ..rest] => len(rest) }", 2).test_me();
+   i64Test::new("match ([[1, 2], {\"k\": 5}]) { [[a, b], {\"k\": c}] => a + b + c }", 8).test_me();
+   i64Test::new("let x = 1; match (5) { x => x }; x", 1).test_me();
+   i64Test::new("let f = fn(n) { match (n) { 0 => { return 10; }, _ => 20 } }; f(0) + f(1)", 30).test_me();
+   i64Test::new("let total = 0; for (n in range(5)) { match (n % 2) { 0 => { continue; }, _ => { total += n; } } } total", 4).test_me();
+   IfElseTest::new("match (5) { 1 => 2 }", None).test_me();
+   IfElseTest::new("let y = match (1) { 1 => {} }; y", None).test_me();
+   IfElseTest::new("let f = fn() { match (1) { _ => { } } }; f()", None).test_me();
+   ErrorMessageTest::new("match (1 / 0) { _ => 1 }", "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new("match (1) { n if n + true => 1 }", "type mismatch: INTEGER + BOOLEAN").test_me();
+}
+
+#[test]
+fn test_destructuring_let() {
+   i64Test::new("let [a, b] = [1, 2]; a * 10 + b", 12).test_me();
+   i64Test::new("let [first, ..rest] = [1, 2, 3]; first + len(rest) * 10 + rest[1]", 24).test_me();
+   i64Test::new("let [_, ..] = [1]; let [..all] = []; len(all)", 0).test_me();
+   i64Test::new("let [[a, b], [c]] = [[1, 2], [3]]; a + b + c", 6).test_me();
+   i64Test::new("let {\"name\": n, \"age\": age} = {\"name\": \"Ann\", \"age\": 30, \"extra\": true}; age + len(n)", 33).test_me();
+   i64Test::new("let {\"point\": [x, y], 1: one} = {\"point\": [3, 4], 1: 5}; x * y + one", 17).test_me();
+   i64Test::new("let [a, 2] = [1, 2]; a", 1).test_me();
+
+   ErrorMessageTest::new("let [a, b] = [1]", "cannot destructure: expected an array of 2 elements, got 1").test_me();
+   ErrorMessageTest::new("let [a, b] = [1, 2, 3]", "cannot destructure: expected an array of 2 elements, got 3").test_me();
+   ErrorMessageTest::new("let [a, b, ..c] = [1]", "cannot destructure: expected an array of at least 2 elements, got 1").test_me();
+   ErrorMessageTest::new("let [a] = {\"a\": 1}", "cannot destructure: expected an array, got HASH").test_me();
+   ErrorMessageTest::new("let {\"a\": a} = [1]", "cannot destructure: expected a hash, got ARRAY").test_me();
+   ErrorMessageTest::new("let {\"name\": n} = {\"nme\": 1}", "cannot destructure: missing hash key: name").test_me();
+   ErrorMessageTest::new("let {\"p\": [x, y]} = {\"p\": 5}", "cannot destructure: expected an array, got INTEGER").test_me();
+   ErrorMessageTest::new("let [a, 2] = [1, 3]", "cannot destructure: expected 2, got 3").test_me();
+   ErrorMessageTest::new("let [a] = [1 / 0]", "division by zero: 1 / 0").test_me();
+}
+
+#[test]
+fn test_default_rest_and_named_parameters() {
+   i64Test::new("let f = fn(x, y = 10) { x + y }; f(1)", 11).test_me();
+   i64Test::new("let f = fn(x, y = 10) { x + y }; f(1, 2)", 3).test_me();
+   i64Test::new("let f = fn(x, y = x * 2) { y }; f(4)", 8).test_me();
+   i64Test::new("let n = 0; let f = fn(x = n += 1) { x }; f(); f(); n", 2).test_me();
+   i64Test::new("let f = fn(first, ...others) { first * 100 + len(others) }; f(1) + f(2, 3, 4)", 302).test_me();
+   i64Test::new("let f = fn(...all) { all[2] }; f(1, 2, 3)", 3).test_me();
+   i64Test::new("let f = fn(x, y) { x - y }; f(y: 1, x: 10)", 9).test_me();
+   i64Test::new("let f = fn(x, y = 2, z = 3) { x * 100 + y * 10 + z }; f(1, z: 9)", 129).test_me();
+   i64Test::new("let f = fn(x = 1, y) { x + y }; f(y: 5)", 6).test_me();
+
+   match test_eval(String::from("let f = fn(x, y = 1, ...r) { x }; f")) {
+      Some(eval) => assert_eq!(eval.inspect(), "fn (x, y = 1, ...r) {\nx\n}"),
+      None => panic!("test_eval returned None.")
+   }
+
+   ErrorMessageTest::new("let f = fn(x, y = 1) { x }; f()", "wrong number of arguments: want=1 to 2, got=0 (f defined at 1:9)").test_me();
+   ErrorMessageTest::new("let f = fn(x, y = 1) { x }; f(1, 2, 3)", "wrong number of arguments: want=1 to 2, got=3 (f defined at 1:9)").test_me();
+   ErrorMessageTest::new("let f = fn(x, ...r) { x }; f()", "wrong number of arguments: want=at least 1, got=0 (f defined at 1:9)").test_me();
+   ErrorMessageTest::new("let f = fn(x, y) { x }; f(1, z: 2)", "unexpected named argument: z (f defined at 1:9)").test_me();
+   ErrorMessageTest::new("let f = fn(x, y) { x }; f(1, x: 2)", "multiple values for argument: x (f defined at 1:9)").test_me();
+   ErrorMessageTest::new("let f = fn(x, y) { x }; f(y: 1, y: 2)", "multiple values for argument: y (f defined at 1:9)").test_me();
+   ErrorMessageTest::new("let f = fn(x, y) { x }; f(y: 1)", "missing argument for parameter: x (f defined at 1:9)").test_me();
+   ErrorMessageTest::new("let f = fn(x, ...r) { x }; f(1, r: 2)", "unexpected named argument: r (f defined at 1:9)").test_me();
+   ErrorMessageTest::new("let f = fn(x = 1 / 0) { x }; f()", "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new("len(x: \"a\")", "builtin functions don't take named arguments, got: x").test_me();
+}
+
+#[test]
+fn test_lambdas() {
+   i64Test::new("let double = |x| x * 2; double(21)", 42).test_me();
+   i64Test::new("let five = || 5; five()", 5).test_me();
+   i64Test::new("let add = |a, b = 10| a + b; add(1) + add(1, 2)", 14).test_me();
+   i64Test::new("let apply = fn(f, v) { f(v) }; apply(|x| x - 1, 10)", 9).test_me();
+   i64Test::new("let total = |...xs| { let t = 0; for (x in xs) { t += x; } t }; total(1, 2, 3)", 6).test_me();
+   i64Test::new("let counter = fn() { let n = 0; || { n += 1; n } }; let c = counter(); c(); c(); c()", 3).test_me();
+   i64Test::new("let lengths = |a, b| len(a) + len(b); lengths(\"ab\", [1, 2, 3])", 5).test_me();
+   i64Test::new("let f = |x| { if (x > 0) { return 1; } 2 }; f(1) + f(-1)", 3).test_me();
+
+   ErrorMessageTest::new("let double = |x| x * 2; double()", "wrong number of arguments: want=1, got=0 (double defined at 1:14)").test_me();
+}
+
+#[test]
+fn test_pipeline_operator() {
+   i64Test::new("let double = |x| x * 2; 5 |> double", 10).test_me();
+   i64Test::new("let sub = fn(a, b) { a - b }; 10 |> sub(3)", 7).test_me();
+   i64Test::new("[1, 2, 3] |> push(4) |> len", 4).test_me();
+   i64Test::new("let inc = |x, by = 1| x + by; 1 |> inc |> inc(by: 10)", 12).test_me();
+   i64Test::new("\"abc\" |> |s| len(s) * 2", 6).test_me();
+   i64Test::new("5 |> (fn(a) { fn(b) { a + b } }(1))", 6).test_me();
+   i64Test::new("let add = fn(a) { |b| a + b }; 5 |> (add(1)) |> (add(10))", 16).test_me();
+   ErrorMessageTest::new("5 |> 3", "not a function: INTEGER").test_me();
+}
+
+#[test]
+fn test_conditional_and_null_coalescing() {
+   i64Test::new("true ? 1 : 2", 1).test_me();
+   i64Test::new("0 ? 1 : 2", 1).test_me();
+   i64Test::new("let sign = fn(n) { n < 0 ? -1 : n == 0 ? 0 : 1 }; sign(-5) * 100 + sign(0) * 10 + sign(7)", -99).test_me();
+   i64Test::new("false ? 1 / 0 : 2", 2).test_me();
+   i64Test::new("let h = {}; h[\"missing\"] ?? 5", 5).test_me();
+   i64Test::new("3 ?? 1 / 0", 3).test_me();
+   i64Test::new("let f = fn(x = 0) { x ?? 7 }; f(if (false) { 1 })", 7).test_me();
+   BoolTest::new("false ?? true", false).test_me();
+
+   i64Test::new("let user = {\"address\": {\"zip\": 1234}}; user[\"address\"]?.[\"zip\"]", 1234).test_me();
+   i64Test::new("let user = {}; user[\"address\"]?.[\"zip\"] ?? 0", 0).test_me();
+   i64Test::new("let user = {}; user[\"a\"]?.[\"b\"]?.[\"c\"] ?? -1", -1).test_me();
+   i64Test::new("let a = [[1, 2]]; a[0]?.[1]", 2).test_me();
+   i64Test::new("let n = 0; let h = {}; h[\"x\"]?.[n += 1]; n", 0).test_me();
+
+   ErrorMessageTest::new("(1 / 0) ? 1 : 2", "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new("let h = {}; h[\"x\"] ?? 1 / 0", "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new("let h = {}; h[\"a\"]?.[\"b\"][\"c\"]", "index operator not supported: NULL").test_me();
+   ErrorMessageTest::new("5?.[0]", "index operator not supported: INTEGER").test_me();
+}
+
+#[test]
+fn test_string_index_and_slicing() {
+   let tests: Vec<(&str, &str)> = vec![
+      ("[1, 2, 3, 4][1:3]", "[2, 3]"),
+      ("[1, 2, 3, 4][:-1]", "[1, 2, 3]"),
+      ("[1, 2, 3, 4][2:]", "[3, 4]"),
+      ("[1, 2, 3, 4][:]", "[1, 2, 3, 4]"),
+      ("[1, 2, 3, 4][-2:]", "[3, 4]"),
+      ("[1, 2, 3, 4][1:100]", "[2, 3, 4]"),
+      ("[1, 2, 3, 4][-100:2]", "[1, 2]"),
+      ("[1, 2, 3, 4][3:1]", "[]"),
+      ("[1, 2, 3, 4][10:]", "[]"),
+      ("\"hello\"[1:3]", "el"),
+      ("\"hello\"[2:]", "llo"),
+      ("\"hello\"[:-1]", "hell"),
+      ("\"héllo wörld\"[6:]", "wörld"),
+      ("\"abc\"[0]", "a"),
+      ("\"abc\"[-1]", "c"),
+      ("\"héllo\"[1]", "é"),
+      ("\"abc\"[3]", "null"),
+      ("let h = {}; h[\"list\"]?.[1:]", "null"),
+      ("let a = [1, 2, 3]; let i = 1; a[i:i + 1]", "[2]"),
+   ];
+   for (input, expected) in tests {
+      match test_eval(input.to_string()) {
+         Some(eval) => assert_eq!(eval.inspect(), expected, "\nfor input {}", input),
+         None => panic!("test_eval returned None.")
+      }
+   }
+
+   // A slice is a new array, changing it leaves the original alone
+   i64Test::new("let a = [1, 2, 3]; let b = a[1:]; b[0] = 50; a[1]", 2).test_me();
+
+   ErrorMessageTest::new("5[1:2]", "slice operator not supported: INTEGER").test_me();
+   ErrorMessageTest::new("[1, 2][\"a\":]", "slice bounds must be INTEGER, got STRING").test_me();
+   ErrorMessageTest::new("[1, 2][:1 / 0]", "division by zero: 1 / 0").test_me();
+   ErrorMessageTest::new("\"abc\"[\"a\"]", "index operator not supported: STRING").test_me();
+}
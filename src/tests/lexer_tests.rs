@@ -2,7 +2,8 @@
 #[cfg(test)]
 
 use crate::lexer::Lexer;
-use crate::lexer::token::{Token, TokenType};
+use crate::lexer::token::{Token, TokenType, Span};
+use crate::parser::errors::ParseError;
 
 struct Test {
    expected_tok_type: TokenType,
@@ -58,7 +59,7 @@ fn test_next_token_2() {
          };
 
          let result = add(five, ten);  
-         !-/*5;
+         !-/ *5;
          5 < 10 > 5;
    
          if (5 < 10) {
@@ -187,3 +188,394 @@ fn test_next_token_2() {
       assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
    }
 }
+
+#[test]
+fn test_token_spans() {
+   let input: String = String::from("let five = 5;\n  five == \"foo\";");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let expected: Vec<(TokenType, Span)> = vec![
+      (TokenType::LET, Span::new(0, 3, 1, 1)),
+      (TokenType::IDENT, Span::new(4, 8, 1, 5)),
+      (TokenType::ASSIGN, Span::new(9, 10, 1, 10)),
+      (TokenType::INT, Span::new(11, 12, 1, 12)),
+      (TokenType::SEMICOLON, Span::new(12, 13, 1, 13)),
+      (TokenType::IDENT, Span::new(16, 20, 2, 3)),
+      (TokenType::EQ, Span::new(21, 23, 2, 8)),
+      (TokenType::STRING, Span::new(24, 29, 2, 11)),
+      (TokenType::SEMICOLON, Span::new(29, 30, 2, 16)),
+      (TokenType::EOF, Span::new(30, 30, 2, 17)),
+   ];
+
+   for (expected_type, expected_span) in expected {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, expected_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.span, expected_span, "\nLexer got a different span then expected for {:?}.", tok.token_type);
+   }
+}
+
+#[test]
+fn test_number_literals() {
+   let input: String = String::from("3.14 1e-9 2.5E+3 10 0.5; 7e x 1.0 12.");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let tests: Vec<Test> = vec![
+      Test::new(TokenType::FLOAT, "3.14"),
+      Test::new(TokenType::FLOAT, "1e-9"),
+      Test::new(TokenType::FLOAT, "2.5E+3"),
+      Test::new(TokenType::INT, "10"),
+      Test::new(TokenType::FLOAT, "0.5"),
+      Test::new(TokenType::SEMICOLON, ";"),
+      Test::new(TokenType::INT, "7"),
+      Test::new(TokenType::IDENT, "e"),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::FLOAT, "1.0"),
+      Test::new(TokenType::INT, "12"),
+      Test::new(TokenType::ILLEGAL, "."),
+      Test::new(TokenType::EOF, ""),
+   ];
+
+   for test in tests {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, test.expected_tok_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
+   }
+}
+
+#[test]
+fn test_comparison_and_logical_operators() {
+   let input: String = String::from("a <= b >= c % d && e || f < g > h & | |>");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let tests: Vec<Test> = vec![
+      Test::new(TokenType::IDENT, "a"),
+      Test::new(TokenType::LTEQ, "<="),
+      Test::new(TokenType::IDENT, "b"),
+      Test::new(TokenType::GTEQ, ">="),
+      Test::new(TokenType::IDENT, "c"),
+      Test::new(TokenType::PERCENT, "%"),
+      Test::new(TokenType::IDENT, "d"),
+      Test::new(TokenType::AND, "&&"),
+      Test::new(TokenType::IDENT, "e"),
+      Test::new(TokenType::OR, "||"),
+      Test::new(TokenType::IDENT, "f"),
+      Test::new(TokenType::LT, "<"),
+      Test::new(TokenType::IDENT, "g"),
+      Test::new(TokenType::GT, ">"),
+      Test::new(TokenType::IDENT, "h"),
+      Test::new(TokenType::ILLEGAL, "&"),
+      Test::new(TokenType::PIPE, "|"),
+      Test::new(TokenType::PIPELINE, "|>"),
+      Test::new(TokenType::EOF, ""),
+   ];
+
+   for test in tests {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, test.expected_tok_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
+   }
+}
+
+#[test]
+fn test_comments() {
+   let input: String = String::from("// a line comment\nlet x = 10 / 2; // trailing\n/* block /* nested */ still a comment */ x /**/ /");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let tests: Vec<Test> = vec![
+      Test::new(TokenType::LET, "let"),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::ASSIGN, "="),
+      Test::new(TokenType::INT, "10"),
+      Test::new(TokenType::SLASH, "/"),
+      Test::new(TokenType::INT, "2"),
+      Test::new(TokenType::SEMICOLON, ";"),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::SLASH, "/"),
+      Test::new(TokenType::EOF, ""),
+   ];
+
+   for test in tests {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, test.expected_tok_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
+   }
+   assert!(lexer.errors.is_empty(), "\nLexer reported errors for valid comments: {:?}", lexer.errors);
+}
+
+#[test]
+fn test_unterminated_block_comment() {
+   let input: String = String::from("let x = 1;\n/* outer /* inner */ never closed\nlet y = 2;");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let mut tok: Token = lexer.next_token().unwrap();
+   while tok.token_type != TokenType::EOF {
+      tok = lexer.next_token().unwrap();
+   }
+
+   assert_eq!(lexer.errors, vec![ParseError::UnterminatedComment { span: Span::new(11, 13, 2, 1) }]);
+}
+
+#[test]
+fn test_string_escapes() {
+   let tests: Vec<(&str, &str)> = vec![
+      (r#""line\nbreak""#, "line\nbreak"),
+      (r#""a\tb\rc""#, "a\tb\rc"),
+      (r#""say \"hi\"""#, "say \"hi\""),
+      (r#""back\\slash""#, "back\\slash"),
+      (r#""nul\0""#, "nul\0"),
+      (r#""\u{1F600} \u{e9}""#, "\u{1F600} \u{e9}"),
+      (r#""""#, ""),
+   ];
+
+   for (input, expected) in tests {
+      let mut lexer: Lexer = Lexer::new(input.to_string());
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, TokenType::STRING);
+      assert_eq!(tok.literal, expected, "\nLexer decoded {} wrong.", input);
+      assert!(lexer.errors.is_empty(), "\nLexer reported errors for {}: {:?}", input, lexer.errors);
+      assert_eq!(lexer.next_token().unwrap().token_type, TokenType::EOF);
+   }
+}
+
+#[test]
+fn test_invalid_string_escapes() {
+   let tests: Vec<(&str, Span, &str)> = vec![
+      (r#""bad \q escape""#, Span::new(5, 7, 1, 6), "unknown escape \\q"),
+      (r#""\u0041""#, Span::new(1, 3, 1, 2), "expected '{' after \\u"),
+      (r#""\u{1F600""#, Span::new(1, 9, 1, 2), "expected '}' to close \\u{...}"),
+      (r#""\u{}""#, Span::new(1, 5, 1, 2), "\\u{} must have between 1 and 6 hex digits"),
+      (r#""\u{D800}""#, Span::new(1, 9, 1, 2), "\\u{D800} is not a valid unicode scalar value"),
+   ];
+
+   for (input, span, reason) in tests {
+      let mut lexer: Lexer = Lexer::new(input.to_string());
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, TokenType::STRING);
+      assert_eq!(lexer.errors, vec![ParseError::InvalidEscape { span, reason: reason.to_string() }], "\nfor input {}", input);
+   }
+
+   // A trailing backslash can't escape the end of the input
+   let mut lexer: Lexer = Lexer::new(String::from(r#""abc\"#));
+   lexer.next_token().unwrap();
+   assert_eq!(lexer.errors, vec![ParseError::UnterminatedString { span: Span::new(0, 5, 1, 1) }]);
+}
+
+#[test]
+fn test_unicode_input() {
+   let input: String = String::from("let café = \"naïve 😀\";\nπ_2 + _x ÷ é");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let expected: Vec<(TokenType, &str, Span)> = vec![
+      (TokenType::LET, "let", Span::new(0, 3, 1, 1)),
+      (TokenType::IDENT, "café", Span::new(4, 9, 1, 5)),
+      (TokenType::ASSIGN, "=", Span::new(10, 11, 1, 10)),
+      (TokenType::STRING, "naïve 😀", Span::new(12, 25, 1, 12)),
+      (TokenType::SEMICOLON, ";", Span::new(25, 26, 1, 21)),
+      (TokenType::IDENT, "π_2", Span::new(27, 31, 2, 1)),
+      (TokenType::PLUS, "+", Span::new(32, 33, 2, 5)),
+      (TokenType::IDENT, "_x", Span::new(34, 36, 2, 7)),
+      (TokenType::ILLEGAL, "÷", Span::new(37, 39, 2, 10)),
+      (TokenType::IDENT, "é", Span::new(40, 42, 2, 12)),
+      (TokenType::EOF, "", Span::new(42, 42, 2, 13)),
+   ];
+
+   for (expected_type, expected_literal, expected_span) in expected {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, expected_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, expected_literal, "\nLexer got a different literal then expected.");
+      assert_eq!(tok.span, expected_span, "\nLexer got a different span then expected for {:?}.", tok.literal);
+   }
+}
+
+#[test]
+fn test_interpolated_strings() {
+   let input: String = String::from(r#""total: ${price * qty}!" "${ {"a": 1}["a"] }" "a ${"b ${c}"} d" "\${x}""#);
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let tests: Vec<Test> = vec![
+      Test::new(TokenType::TEMPLATESTART, "total: "),
+      Test::new(TokenType::IDENT, "price"),
+      Test::new(TokenType::ASTERISK, "*"),
+      Test::new(TokenType::IDENT, "qty"),
+      Test::new(TokenType::TEMPLATEEND, "!"),
+
+      Test::new(TokenType::TEMPLATESTART, ""),
+      Test::new(TokenType::LBRACE, "{"),
+      Test::new(TokenType::STRING, "a"),
+      Test::new(TokenType::COLON, ":"),
+      Test::new(TokenType::INT, "1"),
+      Test::new(TokenType::RBRACE, "}"),
+      Test::new(TokenType::LBRACKET, "["),
+      Test::new(TokenType::STRING, "a"),
+      Test::new(TokenType::RBRACKET, "]"),
+      Test::new(TokenType::TEMPLATEEND, ""),
+
+      Test::new(TokenType::TEMPLATESTART, "a "),
+      Test::new(TokenType::TEMPLATESTART, "b "),
+      Test::new(TokenType::IDENT, "c"),
+      Test::new(TokenType::TEMPLATEEND, ""),
+      Test::new(TokenType::TEMPLATEEND, " d"),
+
+      Test::new(TokenType::STRING, "${x}"),
+      Test::new(TokenType::EOF, ""),
+   ];
+
+   for test in tests {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, test.expected_tok_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
+   }
+   assert!(lexer.errors.is_empty(), "\nLexer reported errors: {:?}", lexer.errors);
+}
+
+#[test]
+fn test_loop_keywords() {
+   let mut lexer: Lexer = Lexer::new(String::from("while break continue whiles"));
+   let tests: Vec<Test> = vec![
+      Test::new(TokenType::WHILE, "while"),
+      Test::new(TokenType::BREAK, "break"),
+      Test::new(TokenType::CONTINUE, "continue"),
+      Test::new(TokenType::IDENT, "whiles"),
+      Test::new(TokenType::EOF, ""),
+   ];
+
+   for test in tests {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, test.expected_tok_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
+   }
+}
+
+#[test]
+fn test_assignment_operators() {
+   let mut lexer: Lexer = Lexer::new(String::from("x = 1; x += 2; x -= 3; x *= 4; x /= 5; x == x"));
+   let tests: Vec<Test> = vec![
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::ASSIGN, "="),
+      Test::new(TokenType::INT, "1"),
+      Test::new(TokenType::SEMICOLON, ";"),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::PLUSASSIGN, "+="),
+      Test::new(TokenType::INT, "2"),
+      Test::new(TokenType::SEMICOLON, ";"),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::MINUSASSIGN, "-="),
+      Test::new(TokenType::INT, "3"),
+      Test::new(TokenType::SEMICOLON, ";"),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::ASTERISKASSIGN, "*="),
+      Test::new(TokenType::INT, "4"),
+      Test::new(TokenType::SEMICOLON, ";"),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::SLASHASSIGN, "/="),
+      Test::new(TokenType::INT, "5"),
+      Test::new(TokenType::SEMICOLON, ";"),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::EQ, "=="),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::EOF, ""),
+   ];
+
+   for test in tests {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, test.expected_tok_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
+   }
+}
+
+#[test]
+fn test_match_tokens() {
+   let input: String = String::from("match (x) { 1 | 2 => a, [h, ..t] => b, _ => c }");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let tests: Vec<Test> = vec![
+      Test::new(TokenType::MATCH, "match"),
+      Test::new(TokenType::LPAREN, "("),
+      Test::new(TokenType::IDENT, "x"),
+      Test::new(TokenType::RPAREN, ")"),
+      Test::new(TokenType::LBRACE, "{"),
+      Test::new(TokenType::INT, "1"),
+      Test::new(TokenType::PIPE, "|"),
+      Test::new(TokenType::INT, "2"),
+      Test::new(TokenType::FATARROW, "=>"),
+      Test::new(TokenType::IDENT, "a"),
+      Test::new(TokenType::COMMA, ","),
+      Test::new(TokenType::LBRACKET, "["),
+      Test::new(TokenType::IDENT, "h"),
+      Test::new(TokenType::COMMA, ","),
+      Test::new(TokenType::DOTDOT, ".."),
+      Test::new(TokenType::IDENT, "t"),
+      Test::new(TokenType::RBRACKET, "]"),
+      Test::new(TokenType::FATARROW, "=>"),
+      Test::new(TokenType::IDENT, "b"),
+      Test::new(TokenType::COMMA, ","),
+      Test::new(TokenType::IDENT, "_"),
+      Test::new(TokenType::FATARROW, "=>"),
+      Test::new(TokenType::IDENT, "c"),
+      Test::new(TokenType::RBRACE, "}"),
+      Test::new(TokenType::EOF, ""),
+   ];
+
+   for test in tests {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, test.expected_tok_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
+   }
+}
+
+#[test]
+fn test_ellipsis() {
+   let input: String = String::from("fn(a, ...rest) [a, ..] ....");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let tests: Vec<Test> = vec![
+      Test::new(TokenType::FUNCTION, "fn"),
+      Test::new(TokenType::LPAREN, "("),
+      Test::new(TokenType::IDENT, "a"),
+      Test::new(TokenType::COMMA, ","),
+      Test::new(TokenType::ELLIPSIS, "..."),
+      Test::new(TokenType::IDENT, "rest"),
+      Test::new(TokenType::RPAREN, ")"),
+      Test::new(TokenType::LBRACKET, "["),
+      Test::new(TokenType::IDENT, "a"),
+      Test::new(TokenType::COMMA, ","),
+      Test::new(TokenType::DOTDOT, ".."),
+      Test::new(TokenType::RBRACKET, "]"),
+      Test::new(TokenType::ELLIPSIS, "..."),
+      Test::new(TokenType::ILLEGAL, "."),
+      Test::new(TokenType::EOF, ""),
+   ];
+
+   for test in tests {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, test.expected_tok_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
+   }
+}
+
+#[test]
+fn test_question_mark_operators() {
+   let input: String = String::from("a ? b : c ?? d?.[e]");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let tests: Vec<Test> = vec![
+      Test::new(TokenType::IDENT, "a"),
+      Test::new(TokenType::QUESTION, "?"),
+      Test::new(TokenType::IDENT, "b"),
+      Test::new(TokenType::COLON, ":"),
+      Test::new(TokenType::IDENT, "c"),
+      Test::new(TokenType::NULLISH, "??"),
+      Test::new(TokenType::IDENT, "d"),
+      Test::new(TokenType::QUESTIONDOT, "?."),
+      Test::new(TokenType::LBRACKET, "["),
+      Test::new(TokenType::IDENT, "e"),
+      Test::new(TokenType::RBRACKET, "]"),
+      Test::new(TokenType::EOF, ""),
+   ];
+
+   for test in tests {
+      let tok: Token = lexer.next_token().unwrap();
+      assert_eq!(tok.token_type, test.expected_tok_type, "\nLexer got a different token type then expected.");
+      assert_eq!(tok.literal, test.expected_literal, "\nLexer got a different literal then expected.");
+   }
+}
@@ -1,6 +1,6 @@
 #[cfg(test)]
 
-use crate::objects::{Hashable, MkyString};
+use crate::objects::{Hashable, MkyString, Float, Integer, Object};
 
 
 #[test]
@@ -14,3 +14,12 @@ fn test_string_hash_key() {
    assert_eq!(diff1.hash_key(), diff2.hash_key(), "\nstrings with same content have different hash keys");
    assert_ne!(hello1.hash_key(), diff1.hash_key(), "\nstrings with different content have same hash keys");
 }
+
+#[test]
+fn test_float_hash_key() {
+   assert_eq!((Float { value: 2.5 }).hash_key(), (Float { value: 2.5 }).hash_key(), "\nfloats with same value have different hash keys");
+   assert_ne!((Float { value: 2.5 }).hash_key(), (Float { value: 3.5 }).hash_key(), "\nfloats with different values have same hash keys");
+   assert_eq!((Float { value: 2.0 }).hash_key(), (Integer { value: 2 }).hash_key(), "\nintegral float hashes differently from the equal integer");
+   assert_eq!((Float { value: -0.0 }).hash_key(), (Float { value: 0.0 }).hash_key(), "\n-0.0 and 0.0 have different hash keys");
+   assert!(!(Float { value: f64::NAN }).is_hashable(), "\nNaN should not be hashable");
+}
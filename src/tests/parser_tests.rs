@@ -4,9 +4,11 @@ use crate::objects::MkyString;
 #[cfg(test)]
 
 use crate::parser::ast::Program;
-use crate::parser::ast::{Statement, LetStatement, Node, ReturnStatement, ExpressionStatement, Identifier, IntegerLiteral, Expression, PrefixExpression, InfixExpression, Boolean, IfExpression, FunctionLiteral, CallExpression, BlockStatement, StringLiteral, ArrayLiteral, IndexExpression, HashLiteral};
+use crate::parser::ast::{Statement, LetStatement, Node, ReturnStatement, ExpressionStatement, Identifier, IntegerLiteral, FloatLiteral, InterpolatedString, WhileStatement, ForStatement, ContinueStatement, Expression, PrefixExpression, InfixExpression, Boolean, IfExpression, FunctionLiteral, CallExpression, BlockStatement, StringLiteral, ArrayLiteral, IndexExpression, HashLiteral, MatchExpression, Pattern, DestructuringLetStatement, NamedArgument};
 use crate::lexer::Lexer;
+use crate::lexer::token::Span;
 use crate::parser::Parser;
+use crate::parser::errors::ParseError;
 
 use color_eyre::owo_colors::OwoColorize;
 use std::any::Any;
@@ -301,7 +303,7 @@ fn test_let_statement(statement: &Box<dyn Statement>, name: &str) {
 }
 
 fn check_parser_errors(parser: &Parser) {
-   let errors: &Vec<String> = parser.errors();
+   let errors: &Vec<ParseError> = parser.errors();
 
    if errors.len() == 0 {
       return;
@@ -466,6 +468,11 @@ fn test_parsing_infix_expressions() {
    InfixTest::new("5 < 5", 5, "<", 5).test_me();
    InfixTest::new("5 == 5", 5, "==", 5).test_me();
    InfixTest::new("5 != 5", 5, "!=", 5).test_me();
+   InfixTest::new("5 % 5", 5, "%", 5).test_me();
+   InfixTest::new("5 <= 5", 5, "<=", 5).test_me();
+   InfixTest::new("5 >= 5", 5, ">=", 5).test_me();
+   InfixTest::new("true && false", true, "&&", false).test_me();
+   InfixTest::new("true || false", true, "||", false).test_me();
    
    InfixTest::new("true == true", true, "==", true).test_me();
    InfixTest::new("true != false", true, "!=", false).test_me();
@@ -503,6 +510,52 @@ fn test_operator_precedence_parsing() {
 
    Test::new("a * [1, 2, 3, 4][b * c] * d", "((a * ([1, 2, 3, 4][(b * c)])) * d)").test_me();
    Test::new("add(a * b[2], b[1], 2 * [1, 2][1])", "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))").test_me();
+
+   Test::new("a + b % c * d", "(a + ((b % c) * d))").test_me();
+   Test::new("a <= b == c >= d", "((a <= b) == (c >= d))").test_me();
+   Test::new("a || b && c", "(a || (b && c))").test_me();
+   Test::new("a && b || c && d", "((a && b) || (c && d))").test_me();
+   Test::new("a == b && c != d", "((a == b) && (c != d))").test_me();
+   Test::new("!a || b < c", "((!a) || (b < c))").test_me();
+
+   Test::new("x = 5", "(x = 5)").test_me();
+   Test::new("x = y = 5", "(x = (y = 5))").test_me();
+   Test::new("x += y * 2", "(x += (y * 2))").test_me();
+   Test::new("x -= a || b", "(x -= (a || b))").test_me();
+   Test::new("f(x *= 2, y /= 3)", "f((x *= 2), (y /= 3))").test_me();
+   Test::new("a[0] = b[1] + 1", "((a[0]) = ((b[1]) + 1))").test_me();
+   Test::new("h[\"k\"][i] += 2", "(((h[k])[i]) += 2)").test_me();
+
+   Test::new("x |> f", "f(x)").test_me();
+   Test::new("x |> f(a, b)", "f(x, a, b)").test_me();
+   Test::new("x |> f |> g(1) |> h", "h(g(f(x), 1))").test_me();
+   Test::new("a + b |> f", "f((a + b))").test_me();
+   Test::new("a || b |> f", "f((a || b))").test_me();
+   Test::new("y = x |> f", "(y = f(x))").test_me();
+   Test::new("x |> f(y: 2)", "f(x, y: 2)").test_me();
+   Test::new("x |> m[\"f\"]", "(m[f])(x)").test_me();
+   // A parenthesized call is made first, then its result is called with x
+   Test::new("x |> (f(1))", "f(1)(x)").test_me();
+   Test::new("x |> (f)(1)", "f(x, 1)").test_me();
+
+   Test::new("a ? b : c", "(a ? b : c)").test_me();
+   Test::new("a ? b : c ? d : e", "(a ? b : (c ? d : e))").test_me();
+   Test::new("a < b ? a + 1 : b * 2", "((a < b) ? (a + 1) : (b * 2))").test_me();
+   Test::new("a || b ? c : d", "((a || b) ? c : d)").test_me();
+   Test::new("x = a ? b : c", "(x = (a ? b : c))").test_me();
+   Test::new("a ? b ? c : d : e", "(a ? (b ? c : d) : e)").test_me();
+   Test::new("a ?? b ?? c", "((a ?? b) ?? c)").test_me();
+   Test::new("a ?? b || c", "(a ?? (b || c))").test_me();
+   Test::new("a ?? b ? c : d", "((a ?? b) ? c : d)").test_me();
+   Test::new("a?.[\"k\"]?.[0] ?? d", "(((a?.[k])?.[0]) ?? d)").test_me();
+
+   Test::new("a[1:3]", "(a[1:3])").test_me();
+   Test::new("a[:-1]", "(a[:(-1)])").test_me();
+   Test::new("s[2:]", "(s[2:])").test_me();
+   Test::new("s[:]", "(s[:])").test_me();
+   Test::new("a[i + 1:len(a)][0]", "((a[(i + 1):len(a)])[0])").test_me();
+   Test::new("a?.[1:]", "(a?.[1:])").test_me();
+   Test::new("a[c ? 1 : 2:]", "(a[(c ? 1 : 2):])").test_me();
 }
 
 #[test]
@@ -778,3 +831,618 @@ fn test_parsing_empty_hash_literal() {
       }
    }
 }
+
+#[test]
+fn test_node_spans() {
+   let input: String = String::from("let x = add(1, 2 * 3);\nx + [1, 2][0];");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = match parser.parse_program() {
+      Ok(program) => program,
+      Err(e) => panic!("{}", e),
+   }; 
+   check_parser_errors(&parser); 
+   assert_eq!(program.statements.len(), 2);
+   assert_eq!(program.span(), Span::new(0, 37, 1, 1));
+
+   let let_stmt: &LetStatement = program.statements.first().unwrap().as_any().downcast_ref::<LetStatement>().unwrap();
+   assert_eq!(let_stmt.span, Span::new(0, 22, 1, 1));
+   assert_eq!(let_stmt.name.span(), Span::new(4, 5, 1, 5));
+
+   let call_expr: &CallExpression = let_stmt.value.as_ref().unwrap().as_any().downcast_ref::<CallExpression>().unwrap();
+   assert_eq!(call_expr.span, Span::new(8, 21, 1, 9));
+   assert_eq!(call_expr.arguments.as_ref().unwrap().get(1).unwrap().span(), Span::new(15, 20, 1, 16));
+
+   let expr_stmt: &ExpressionStatement = program.statements.get(1).unwrap().as_any().downcast_ref::<ExpressionStatement>().unwrap();
+   assert_eq!(expr_stmt.span, Span::new(23, 37, 2, 1));
+
+   let infix_expr: &InfixExpression = expr_stmt.expression.as_ref().unwrap().as_any().downcast_ref::<InfixExpression>().unwrap();
+   assert_eq!(infix_expr.span, Span::new(23, 36, 2, 1));
+   assert_eq!(infix_expr.right.as_ref().unwrap().span(), Span::new(27, 36, 2, 5));
+}
+
+fn parse_errors(input: &str) -> Vec<ParseError> {
+   let mut lexer: Lexer = Lexer::new(input.to_string());
+   let mut parser: Parser = Parser::new(lexer);
+   match parser.parse_program() {
+      Ok(_) => parser.errors().clone(),
+      Err(e) => panic!("{}", e),
+   }
+}
+
+#[test]
+fn test_parse_error_kinds() {
+   let errors: Vec<ParseError> = parse_errors("let x 5;");
+   assert_eq!(errors.len(), 1);
+   match errors.first().unwrap() {
+      ParseError::ExpectedToken { expected, found } => {
+         assert_eq!(*expected, crate::lexer::token::TokenType::ASSIGN);
+         assert_eq!(found.literal, "5");
+         assert_eq!(found.span, Span::new(6, 7, 1, 7));
+      },
+      other => panic!("expected ExpectedToken, got {:?}", other)
+   }
+
+   let errors: Vec<ParseError> = parse_errors("let x = 99999999999999999999;");
+   assert_eq!(errors.len(), 1);
+   assert!(matches!(errors.first().unwrap(), ParseError::BadIntegerLiteral { .. }), "got {:?}", errors);
+
+   let errors: Vec<ParseError> = parse_errors("let x = @;");
+   assert_eq!(errors.len(), 1);
+   assert!(matches!(errors.first().unwrap(), ParseError::IllegalCharacter { .. }), "got {:?}", errors);
+
+   let errors: Vec<ParseError> = parse_errors("let x = \"hello");
+   assert_eq!(errors.len(), 1);
+   assert_eq!(errors.first().unwrap(), &ParseError::UnterminatedString { span: Span::new(8, 14, 1, 9) });
+
+   let errors: Vec<ParseError> = parse_errors("let x = ;");
+   assert_eq!(errors.len(), 1);
+   assert!(matches!(errors.first().unwrap(), ParseError::NoPrefixParseFn { .. }), "got {:?}", errors);
+}
+
+#[test]
+fn test_parse_error_rendering() {
+   let source: &str = "let a = 1;\nlet foo 10;";
+   let errors: Vec<ParseError> = parse_errors(source);
+   assert_eq!(errors.len(), 1);
+
+   let expected: &str = "\
+error: Expected next token to be ASSIGN, got INT instead.
+ --> 2:9
+  |
+2 | let foo 10;
+  |         ^^
+";
+   assert_eq!(errors.first().unwrap().render(source), expected);
+}
+
+#[test]
+fn test_error_recovery() {
+   let input: String = String::from("
+      let x 5;
+      let add = fn(a, b) { a + ; };
+      let ok = 1;
+      if (x > 10 { print(x) }
+      let fine = add(1, 2);
+   ");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = match parser.parse_program() {
+      Ok(program) => program,
+      Err(e) => panic!("{}", e),
+   };
+
+   let errors: Vec<String> = parser.errors().iter().map(|e| e.to_string()).collect();
+   assert_eq!(errors, vec![
+      "Expected next token to be ASSIGN, got INT instead.",
+      "No prefix parse function for SEMICOLON found.",
+      "Expected next token to be RPAREN, got LBRACE instead.",
+   ]);
+
+   // Statements around the mistakes are still parsed
+   let names: Vec<String> = program.statements.iter()
+      .filter_map(|s| s.as_any().downcast_ref::<LetStatement>())
+      .map(|l| l.name.value.clone())
+      .collect();
+   assert_eq!(names, vec!["add", "ok", "fine"]);
+}
+
+#[test]
+fn test_error_recovery_in_nested_blocks() {
+   let input: String = String::from("let f = fn() {\n if (true) { let y = ; }\n let a = 1 +;\n};\nlet z = (;\nlet ok = 1;");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = match parser.parse_program() {
+      Ok(program) => program,
+      Err(e) => panic!("{}", e),
+   };
+
+   // The error inside the if was already dealt with, so the function body doesn't stop at the if's closing brace
+   let errors: Vec<(String, Span)> = parser.errors().iter().map(|e| (e.to_string(), e.span())).collect();
+   assert_eq!(errors, vec![
+      (String::from("No prefix parse function for SEMICOLON found."), Span::new(36, 37, 2, 22)),
+      (String::from("No prefix parse function for SEMICOLON found."), Span::new(52, 53, 3, 13)),
+      (String::from("No prefix parse function for SEMICOLON found."), Span::new(66, 67, 5, 10)),
+   ]);
+
+   let names: Vec<String> = program.statements.iter()
+      .filter_map(|s| s.as_any().downcast_ref::<LetStatement>())
+      .map(|l| l.name.value.clone())
+      .collect();
+   assert_eq!(names, vec!["f", "ok"]);
+}
+
+#[test]
+fn test_error_recovery_in_call_arguments() {
+   // A bad argument fails the whole call, so the closing parenthesis isn't parsed as a statement of its own
+   let errors: Vec<ParseError> = parse_errors("f(99999999999999999999);");
+   assert_eq!(errors.len(), 1, "got {:?}", errors);
+   assert!(matches!(errors.first().unwrap(), ParseError::BadIntegerLiteral { .. }), "got {:?}", errors);
+
+   let input: String = String::from("f(99999999999999999999);\nlet a = add(1, 2;\nputs(len(x y));\nlet ok = f(1);");
+
+   let mut lexer: Lexer = Lexer::new(input);
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = match parser.parse_program() {
+      Ok(program) => program,
+      Err(e) => panic!("{}", e),
+   };
+
+   let errors: Vec<(String, Span)> = parser.errors().iter().map(|e| (e.to_string(), e.span())).collect();
+   assert_eq!(errors, vec![
+      (String::from("Could not parse 99999999999999999999 as i64. Error: number too large to fit in target type"), Span::new(2, 22, 1, 3)),
+      (String::from("Expected next token to be RPAREN, got SEMICOLON instead."), Span::new(41, 42, 2, 17)),
+      (String::from("Expected next token to be RPAREN, got IDENT instead."), Span::new(54, 55, 3, 12)),
+   ]);
+
+   let names: Vec<String> = program.statements.iter()
+      .filter_map(|s| s.as_any().downcast_ref::<LetStatement>())
+      .map(|l| l.name.value.clone())
+      .collect();
+   assert_eq!(names, vec!["ok"]);
+}
+
+#[test]
+fn test_malformed_input_does_not_panic() {
+   let inputs: Vec<&str> = vec![
+      "(", ")", "[", "]", "{", "}", "fn", "fn(", "fn(1) {}", "fn(x, ) {}", "let", "let x =", "return",
+      "add(1, ", "add(1 2)", "[1, 2", "arr[", "arr[1", "{\"a\": }", "{\"a\" 1", "{1: 2,", "if", "if (x", 
+      "if (x) {", "if (x) { 1 } else", "if (x) { 1 } else {", "-", "!", "1 +", "\"abc", "@", "let @ = 1;",
+   ];
+
+   for input in inputs {
+      let errors: Vec<ParseError> = parse_errors(input);
+      assert!(!errors.is_empty(), "expected errors for {:?}", input);
+   }
+}
+
+#[test]
+fn test_float_literal_expression() {
+   let tests: Vec<(&str, f64)> = vec![("1.25;", 1.25), ("1e-9", 1e-9), ("2.5E3", 2500.0)];
+
+   for (input, expected) in tests {
+      let mut lexer: Lexer = Lexer::new(input.to_string());
+      let mut parser: Parser = Parser::new(lexer);
+      let program: Program = match parser.parse_program() {
+         Ok(program) => program,
+         Err(e) => panic!("{}", e),
+      }; 
+      check_parser_errors(&parser);
+
+      let stmt: &ExpressionStatement = program.statements.first().unwrap().as_any().downcast_ref::<ExpressionStatement>().unwrap();
+      if let Some(literal) = stmt.expression.as_ref().unwrap().as_any().downcast_ref::<FloatLiteral>() {
+         assert_eq!(literal.value, expected)
+      } else {
+         panic!("exp is not FloatLiteral")
+      }
+   }
+
+   let errors: Vec<ParseError> = parse_errors("1e999");
+   assert!(matches!(errors.first().unwrap(), ParseError::BadFloatLiteral { .. }), "got {:?}", errors);
+}
+
+#[test]
+fn test_interpolated_string_parsing() {
+   let input: &str = r#""a ${x + 1} b ${f(y)}""#;
+   let mut lexer: Lexer = Lexer::new(input.to_string());
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = match parser.parse_program() {
+      Ok(program) => program,
+      Err(e) => panic!("{}", e),
+   };
+   check_parser_errors(&parser);
+
+   let stmt: &ExpressionStatement = program.statements.first().unwrap().as_any().downcast_ref::<ExpressionStatement>().unwrap();
+   let interpolated: &InterpolatedString = match stmt.expression.as_ref().unwrap().as_any().downcast_ref::<InterpolatedString>() {
+      Some(interpolated) => interpolated,
+      None => panic!("exp is not InterpolatedString")
+   };
+   assert_eq!(interpolated.strings, vec!["a ", " b ", ""]);
+   assert_eq!(interpolated.expressions.len(), 2);
+   assert_eq!(interpolated.expressions.first().unwrap().string(), "(x + 1)");
+   assert_eq!(interpolated.expressions.get(1).unwrap().string(), "f(y)");
+   assert_eq!(program.string(), "a ${(x + 1)} b ${f(y)}");
+   assert_eq!(interpolated.span, Span::new(0, 22, 1, 1));
+
+   let errors: Vec<ParseError> = parse_errors(r#""${x y}""#);
+   assert_eq!(errors.len(), 1);
+   match errors.first().unwrap() {
+      ParseError::ExpectedToken { expected, found } => {
+         assert_eq!(*expected, crate::lexer::token::TokenType::TEMPLATEEND);
+         assert_eq!(found.literal, "y");
+      },
+      other => panic!("expected ExpectedToken, got {:?}", other)
+   }
+}
+
+#[test]
+fn test_while_statement_parsing() {
+   let input: &str = "while (x < 10) { if (x == 5) { break; } continue; }";
+   let mut lexer: Lexer = Lexer::new(input.to_string());
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = match parser.parse_program() {
+      Ok(program) => program,
+      Err(e) => panic!("{}", e),
+   };
+   check_parser_errors(&parser);
+
+   assert_eq!(program.statements.len(), 1);
+   let while_stmt: &WhileStatement = match program.statements.first().unwrap().as_any().downcast_ref::<WhileStatement>() {
+      Some(while_stmt) => while_stmt,
+      None => panic!("statement is not a WhileStatement")
+   };
+   assert_eq!(while_stmt.condition.string(), "(x < 10)");
+   assert_eq!(while_stmt.body.statements.len(), 2);
+   assert!(while_stmt.body.statements.get(1).unwrap().as_any().is::<ContinueStatement>());
+   assert_eq!(program.string(), "while (x < 10) if (x == 5) break;continue;");
+   assert_eq!(while_stmt.span, Span::new(0, input.len(), 1, 1));
+   // A trailing semicolon belongs to the loop, like it does for let and return
+   let mut lexer: Lexer = Lexer::new("while (x) { x }; y".to_string());
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = parser.parse_program().unwrap();
+   check_parser_errors(&parser);
+   assert_eq!(program.statements.len(), 2);
+   assert!(program.statements.first().unwrap().as_any().is::<WhileStatement>());
+}
+
+#[test]
+fn test_loop_control_outside_loop() {
+   let errors: Vec<ParseError> = parse_errors("break;");
+   assert_eq!(errors.len(), 1);
+   assert!(matches!(errors.first().unwrap(), ParseError::OutsideLoop { token } if token.literal == "break"), "got {:?}", errors);
+
+   // A function literal starts a new context, even when it is written inside a loop
+   let errors: Vec<ParseError> = parse_errors("while (true) { let f = fn() { continue; }; break; }");
+   assert_eq!(errors.len(), 1);
+   assert!(matches!(errors.first().unwrap(), ParseError::OutsideLoop { token } if token.literal == "continue"), "got {:?}", errors);
+   assert_eq!(errors.first().unwrap().to_string(), "'continue' outside of a loop.");
+
+   assert!(parse_errors("while (a) { while (b) { break; } continue; }").is_empty());
+}
+
+#[test]
+fn test_for_statement_parsing() {
+   let tests: Vec<(&str, Vec<&str>, &str)> = vec![
+      ("for (x in arr) { print(x); }", vec!["x"], "for (x in arr) print(x)"),
+      ("for (k, v in {\"a\": 1}) { break; }", vec!["k", "v"], "for (k, v in {a:1}) break;"),
+      ("for (c in range(1, 3)) { continue }", vec!["c"], "for (c in range(1, 3)) continue;"),
+   ];
+
+   for (input, variables, expected) in tests {
+      let mut lexer: Lexer = Lexer::new(input.to_string());
+      let mut parser: Parser = Parser::new(lexer);
+      let program: Program = match parser.parse_program() {
+         Ok(program) => program,
+         Err(e) => panic!("{}", e),
+      };
+      check_parser_errors(&parser);
+
+      let for_stmt: &ForStatement = match program.statements.first().unwrap().as_any().downcast_ref::<ForStatement>() {
+         Some(for_stmt) => for_stmt,
+         None => panic!("statement is not a ForStatement")
+      };
+      let names: Vec<&str> = for_stmt.variables.iter().map(|v| v.value.as_str()).collect();
+      assert_eq!(names, variables);
+      assert_eq!(program.string(), expected);
+   }
+
+   let errors: Vec<ParseError> = parse_errors("for (x of arr) { }");
+   assert_eq!(errors.len(), 1);
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::IN, .. }), "got {:?}", errors);
+
+   assert!(parse_errors("for (x in [1]) { x }; x").is_empty());
+
+   let errors: Vec<ParseError> = parse_errors("for (a, b, c in arr) { }");
+   assert_eq!(errors.len(), 1);
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::IN, .. }), "got {:?}", errors);
+}
+
+#[test]
+fn test_invalid_assignment_targets() {
+   let tests: Vec<(&str, &str)> = vec![
+      ("5 = x", "Cannot assign to 5."),
+      ("a + b = c", "Cannot assign to (a + b)."),
+      ("f() += 1", "Cannot assign to f()."),
+   ];
+
+   for (input, message) in tests {
+      let errors: Vec<ParseError> = parse_errors(input);
+      assert_eq!(errors.len(), 1, "\nfor input {}: {:?}", input, errors);
+      assert!(matches!(errors.first().unwrap(), ParseError::InvalidAssignmentTarget { .. }), "got {:?}", errors);
+      assert_eq!(errors.first().unwrap().to_string(), message);
+   }
+
+   let errors: Vec<ParseError> = parse_errors("a + b = c");
+   assert_eq!(errors.first().unwrap().span(), Span::new(0, 5, 1, 1));
+}
+
+#[test]
+fn test_else_if_expression() {
+   let input: &str = "if (x < 1) { a } else if (x < 2) { b } else if (x < 3) { c } else { d }";
+   let mut lexer: Lexer = Lexer::new(input.to_string());
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = match parser.parse_program() {
+      Ok(program) => program,
+      Err(e) => panic!("{}", e),
+   };
+   check_parser_errors(&parser);
+
+   assert_eq!(program.statements.len(), 1);
+   assert_eq!(program.string(), "if (x < 1) a else if (x < 2) b else if (x < 3) c else d");
+
+   // Each else if is an else block holding the next IfExpression of the chain
+   let stmt: &ExpressionStatement = program.statements.first().unwrap().as_any().downcast_ref::<ExpressionStatement>().unwrap();
+   let mut if_expr: &IfExpression = stmt.expression.as_ref().unwrap().as_any().downcast_ref::<IfExpression>().unwrap();
+   for expected_condition in ["(x < 2)", "(x < 3)"] {
+      let alternative: &BlockStatement = if_expr.alternative.as_ref().unwrap();
+      assert_eq!(alternative.statements.len(), 1);
+      assert_eq!(alternative.token_literal(), "if");
+      let nested: &ExpressionStatement = alternative.statements.first().unwrap().as_any().downcast_ref::<ExpressionStatement>().unwrap();
+      if_expr = nested.expression.as_ref().unwrap().as_any().downcast_ref::<IfExpression>().unwrap();
+      assert_eq!(if_expr.condition.as_ref().unwrap().string(), expected_condition);
+   }
+   assert_eq!(if_expr.alternative.as_ref().unwrap().string(), "d");
+   assert_eq!(stmt.span, Span::new(0, input.len(), 1, 1));
+
+   assert_eq!(parse_errors("if (a) { 1 } else if { 2 }").len(), 1);
+   assert_eq!(parse_errors("if (a) { 1 } else if (b) { 2 } else").len(), 1);
+}
+
+#[test]
+fn test_match_expression_parsing() {
+   let tests: Vec<(&str, &str)> = vec![
+      ("match (x) { 1 => a, _ => b }", "match (x) { 1 => a, _ => b }"),
+      ("match (x) { \"a\" | \"b\" => 1, -2 => 2, }", "match (x) { a | b => 1, (-2) => 2 }"),
+      ("match (x) { [first, ..rest] if len(rest) > 0 => first, [] => 0 }", "match (x) { [first, ..rest] if (len(rest) > 0) => first, [] => 0 }"),
+      ("match (x) { [a, ..] => a, {\"k\": v, 1: true} => { v } n => n }", "match (x) { [a, ..] => a, {k: v, 1: true} => v, n => n }"),
+   ];
+
+   for (input, expected) in tests {
+      let mut lexer: Lexer = Lexer::new(input.to_string());
+      let mut parser: Parser = Parser::new(lexer);
+      let program: Program = match parser.parse_program() {
+         Ok(program) => program,
+         Err(e) => panic!("{}", e),
+      };
+      check_parser_errors(&parser);
+
+      assert_eq!(program.statements.len(), 1);
+      assert_eq!(program.string(), expected);
+   }
+
+   let input: &str = "match (x) { [h, ..t] => h, 1 | n => n }";
+   let mut lexer: Lexer = Lexer::new(input.to_string());
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = parser.parse_program().unwrap();
+   check_parser_errors(&parser);
+
+   let stmt: &ExpressionStatement = program.statements.first().unwrap().as_any().downcast_ref::<ExpressionStatement>().unwrap();
+   let match_expr: &MatchExpression = stmt.expression.as_ref().unwrap().as_any().downcast_ref::<MatchExpression>().unwrap();
+   assert_eq!(match_expr.arms.len(), 2);
+   assert!(matches!(&match_expr.arms.get(1).unwrap().pattern, Pattern::Or(alternatives) if alternatives.len() == 2));
+   assert!(match_expr.arms.get(1).unwrap().pattern.is_irrefutable());
+   assert!(matches!(&match_expr.arms.first().unwrap().pattern, Pattern::Array { elements, rest: Some(_), .. } if elements.len() == 1));
+}
+
+#[test]
+fn test_match_expression_errors() {
+   let errors: Vec<ParseError> = parse_errors("match (x) { _ => 1, 2 => 3, n => 4 }");
+   assert_eq!(errors.len(), 2, "got {:?}", errors);
+   assert!(errors.iter().all(|e| matches!(e, ParseError::UnreachableMatchArm { .. })), "got {:?}", errors);
+   assert_eq!(errors.first().unwrap().span(), Span::new(20, 21, 1, 21));
+
+   // The diagnostic doesn't throw the parser out of the enclosing function body
+   let errors: Vec<ParseError> = parse_errors("fn(x) { match (x) { _ => 1, 2 => 2 } }");
+   assert_eq!(errors.len(), 1, "got {:?}", errors);
+   assert!(matches!(errors.first().unwrap(), ParseError::UnreachableMatchArm { .. }), "got {:?}", errors);
+
+   // A guarded catch-all can still fall through to the arms below it
+   assert!(parse_errors("match (x) { n if n > 1 => 1, _ => 2 }").is_empty());
+
+   let errors: Vec<ParseError> = parse_errors("match (x) { a + b => 1 }");
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::FATARROW, .. }), "got {:?}", errors);
+
+   let errors: Vec<ParseError> = parse_errors("match (x) { fn => 1 }");
+   assert!(matches!(errors.first().unwrap(), ParseError::InvalidPattern { .. }), "got {:?}", errors);
+   assert_eq!(errors.first().unwrap().to_string(), "Expected a pattern, got FUNCTION instead.");
+
+   let errors: Vec<ParseError> = parse_errors("match (x) { [..rest, last] => 1 }");
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::RBRACKET, .. }), "got {:?}", errors);
+}
+
+#[test]
+fn test_destructuring_let_statements() {
+   let tests: Vec<(&str, &str)> = vec![
+      ("let [a, b, ..rest] = arr;", "let [a, b, ..rest] = arr;"),
+      ("let [_, [x, y]] = pairs", "let [_, [x, y]] = pairs;"),
+      ("let {\"name\": n, \"tags\": [first, ..]} = person;", "let {name: n, tags: [first, ..]} = person;"),
+   ];
+
+   for (input, expected) in tests {
+      let mut lexer: Lexer = Lexer::new(input.to_string());
+      let mut parser: Parser = Parser::new(lexer);
+      let program: Program = match parser.parse_program() {
+         Ok(program) => program,
+         Err(e) => panic!("{}", e),
+      };
+      check_parser_errors(&parser);
+
+      assert_eq!(program.statements.len(), 1);
+      assert!(program.statements.first().unwrap().as_any().is::<DestructuringLetStatement>(), "\nfor input {}", input);
+      assert_eq!(program.string(), expected);
+   }
+
+   let errors: Vec<ParseError> = parse_errors("let [a, b] 5;");
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::ASSIGN, .. }), "got {:?}", errors);
+
+   let errors: Vec<ParseError> = parse_errors("let {a: 1} = h;");
+   assert!(matches!(errors.first().unwrap(), ParseError::InvalidPattern { .. }), "got {:?}", errors);
+}
+
+#[test]
+fn test_function_parameter_forms() {
+   // Input, parameters, parameters with a default, rest parameter and the program as a string
+   type ParameterFormTest = (&'static str, Vec<&'static str>, Vec<&'static str>, Option<&'static str>, &'static str);
+   let tests: Vec<ParameterFormTest> = vec![
+      ("fn(x, y = 10) { x }", vec!["x", "y"], vec!["y"], None, "fn(x, y = 10)x"),
+      ("fn(first, ...others) { first }", vec!["first"], vec![], Some("others"), "fn(first, ...others)first"),
+      ("fn(a = 1, b = a * 2, ...c) { c }", vec!["a", "b"], vec!["a", "b"], Some("c"), "fn(a = 1, b = (a * 2), ...c)c"),
+      ("fn(...all) { all }", vec![], vec![], Some("all"), "fn(...all)all"),
+   ];
+
+   for (input, params, defaults, rest, expected) in tests {
+      let mut lexer: Lexer = Lexer::new(input.to_string());
+      let mut parser: Parser = Parser::new(lexer);
+      let program: Program = match parser.parse_program() {
+         Ok(program) => program,
+         Err(e) => panic!("{}", e),
+      };
+      check_parser_errors(&parser);
+
+      let stmt: &ExpressionStatement = program.statements.first().unwrap().as_any().downcast_ref::<ExpressionStatement>().unwrap();
+      let function: &FunctionLiteral = stmt.expression.as_ref().unwrap().as_any().downcast_ref::<FunctionLiteral>().unwrap();
+      let names: Vec<&str> = function.params.as_ref().unwrap().iter().map(|p| p.value.as_str()).collect();
+      assert_eq!(names, params);
+      let mut default_names: Vec<&str> = function.defaults.keys().map(|k| k.as_str()).collect();
+      default_names.sort();
+      assert_eq!(default_names, defaults);
+      assert_eq!(function.rest.as_ref().map(|r| r.value.as_str()), rest);
+      assert_eq!(program.string(), expected);
+   }
+
+   let errors: Vec<ParseError> = parse_errors("fn(...rest, last) { }");
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::RPAREN, .. }), "got {:?}", errors);
+
+   let errors: Vec<ParseError> = parse_errors("fn(x = ) { }");
+   assert!(matches!(errors.first().unwrap(), ParseError::NoPrefixParseFn { .. }), "got {:?}", errors);
+}
+
+#[test]
+fn test_named_arguments() {
+   let input: &str = "f(1, y: 2, z: a + b)";
+   let mut lexer: Lexer = Lexer::new(input.to_string());
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = match parser.parse_program() {
+      Ok(program) => program,
+      Err(e) => panic!("{}", e),
+   };
+   check_parser_errors(&parser);
+   assert_eq!(program.string(), "f(1, y: 2, z: (a + b))");
+
+   let stmt: &ExpressionStatement = program.statements.first().unwrap().as_any().downcast_ref::<ExpressionStatement>().unwrap();
+   let call: &CallExpression = stmt.expression.as_ref().unwrap().as_any().downcast_ref::<CallExpression>().unwrap();
+   let arguments: &Vec<Box<dyn Expression>> = call.arguments.as_ref().unwrap();
+   assert_eq!(arguments.len(), 3);
+   assert!(!arguments.first().unwrap().as_any().is::<NamedArgument>());
+   let named: &NamedArgument = arguments.get(1).unwrap().as_any().downcast_ref::<NamedArgument>().unwrap();
+   assert_eq!(named.name.value, "y");
+   assert_eq!(named.span, Span::new(5, 9, 1, 6));
+
+   // A hash literal argument is still just a hash literal
+   assert!(parse_errors("f({a: 1}, b)").is_empty());
+
+   let errors: Vec<ParseError> = parse_errors("f(x: 1, 2)");
+   assert_eq!(errors.len(), 1, "got {:?}", errors);
+   assert!(matches!(errors.first().unwrap(), ParseError::PositionalAfterNamedArgument { .. }), "got {:?}", errors);
+   assert_eq!(errors.first().unwrap().span(), Span::new(8, 9, 1, 9));
+
+   // The diagnostic doesn't throw the parser out of the enclosing blocks
+   let errors: Vec<ParseError> = parse_errors("fn() { if (true) { h(a: 1, 2) } }");
+   assert_eq!(errors.len(), 1, "got {:?}", errors);
+   assert!(matches!(errors.first().unwrap(), ParseError::PositionalAfterNamedArgument { .. }), "got {:?}", errors);
+}
+
+#[test]
+fn test_lambda_literals() {
+   let tests: Vec<(&str, Vec<&str>, &str)> = vec![
+      ("|x| x * 2", vec!["x"], "|x| (x * 2)"),
+      ("|a, b = 1| a + b", vec!["a", "b"], "|a, b = 1| (a + b)"),
+      ("|| 5", vec![], "|| 5"),
+      ("|x, ...rest| { let y = x; y }", vec!["x"], "|x, ...rest| let y = x;y"),
+      ("map(arr, |x| x + 1)", vec!["x"], "map(arr, |x| (x + 1))"),
+   ];
+
+   for (input, params, expected) in tests {
+      let mut lexer: Lexer = Lexer::new(input.to_string());
+      let mut parser: Parser = Parser::new(lexer);
+      let program: Program = match parser.parse_program() {
+         Ok(program) => program,
+         Err(e) => panic!("{}", e),
+      };
+      check_parser_errors(&parser);
+      assert_eq!(program.string(), expected);
+
+      let stmt: &ExpressionStatement = program.statements.first().unwrap().as_any().downcast_ref::<ExpressionStatement>().unwrap();
+      let expression: &Box<dyn Expression> = stmt.expression.as_ref().unwrap();
+      let function: &FunctionLiteral = match expression.as_any().downcast_ref::<CallExpression>() {
+         Some(call) => call.arguments.as_ref().unwrap().get(1).unwrap().as_any().downcast_ref::<FunctionLiteral>().unwrap(),
+         None => expression.as_any().downcast_ref::<FunctionLiteral>().unwrap(),
+      };
+      let names: Vec<&str> = function.params.as_ref().unwrap().iter().map(|p| p.value.as_str()).collect();
+      assert_eq!(names, params);
+   }
+
+   // `||` is still the logical or between two operands
+   let mut lexer: Lexer = Lexer::new("a || |x| x".to_string());
+   let mut parser: Parser = Parser::new(lexer);
+   let program: Program = parser.parse_program().unwrap();
+   check_parser_errors(&parser);
+   assert_eq!(program.string(), "(a || |x| x)");
+
+   let errors: Vec<ParseError> = parse_errors("while (true) { let f = |x| { break; }; }");
+   assert!(matches!(errors.first().unwrap(), ParseError::OutsideLoop { .. }), "got {:?}", errors);
+
+   let errors: Vec<ParseError> = parse_errors("|x x");
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::PIPE, .. }), "got {:?}", errors);
+}
+
+#[test]
+fn test_conditional_and_optional_index_errors() {
+   let errors: Vec<ParseError> = parse_errors("a ? b");
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::COLON, .. }), "got {:?}", errors);
+
+   let errors: Vec<ParseError> = parse_errors("a?.b");
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::LBRACKET, .. }), "got {:?}", errors);
+
+   let errors: Vec<ParseError> = parse_errors("a?.[0] = 1");
+   assert!(matches!(errors.first().unwrap(), ParseError::InvalidAssignmentTarget { .. }), "got {:?}", errors);
+   assert_eq!(errors.first().unwrap().to_string(), "Cannot assign to (a?.[0]).");
+}
+
+#[test]
+fn test_slice_expression_errors() {
+   let errors: Vec<ParseError> = parse_errors("a[1:2:3]");
+   assert!(matches!(errors.first().unwrap(), ParseError::ExpectedToken { expected: crate::lexer::token::TokenType::RBRACKET, .. }), "got {:?}", errors);
+
+   let errors: Vec<ParseError> = parse_errors("a[1:] = 5");
+   assert!(matches!(errors.first().unwrap(), ParseError::InvalidAssignmentTarget { .. }), "got {:?}", errors);
+
+   // A bad slice inside call arguments fails the call as a whole, the rest of the statement isn't reported again
+   let errors: Vec<ParseError> = parse_errors("print(xs[::]);");
+   assert_eq!(errors.len(), 1, "got {:?}", errors);
+   assert!(matches!(errors.first().unwrap(), ParseError::NoPrefixParseFn { .. }), "got {:?}", errors);
+   assert_eq!(errors.first().unwrap().span(), Span::new(10, 11, 1, 11));
+}